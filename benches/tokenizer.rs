@@ -0,0 +1,110 @@
+// tokenizer throughput of the word-at-a-time scan, run with `cargo bench`, and of the
+// byte-at-a-time scan it replaced, run with `cargo bench --features byte-scan`
+//
+// each run saves its numbers and prints the ones of the other scan next to them,
+// the inputs are generated and each one stresses a part of the tokenizer
+use csstree::tokenizer::token::Token;
+use csstree::tokenizer::tokenizer::Tokenizer;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const SIZE: usize = 4 * 1024 * 1024;
+const RUNS: usize = 30;
+
+fn repeat(chunk: &str) -> String {
+    chunk.repeat(SIZE / chunk.len() + 1)
+}
+
+fn inputs() -> Vec<(&'static str, String)> {
+    vec![
+        (
+            "comments",
+            repeat("/* a license header, or a commented out rule\n * .a { color: red } */\n"),
+        ),
+        (
+            "strings",
+            repeat("a{content:\"some text in a string, \\\"quoted\\\" and ünicode\"}\n"),
+        ),
+        (
+            "identifiers",
+            repeat(".navigation-menu-item--active-state > .dropdown-toggle-indicator{}"),
+        ),
+        (
+            "stylesheet",
+            repeat(concat!(
+                "/* buttons */\n",
+                ".btn-primary:hover, .btn-primary:focus {\n",
+                "  background: url(\"images/btn.png\") no-repeat 0 -20px;\n",
+                "  font: 600 14px/1.5 \"Helvetica Neue\", Arial, sans-serif;\n",
+                "  transition: opacity .15s ease-in-out;\n",
+                "}\n",
+            )),
+        ),
+    ]
+}
+
+// the number of tokens so the work can't be optimized away
+fn tokenize(input: &str) -> usize {
+    let mut tokenizer = Tokenizer::new(input);
+    let mut count = 0;
+    while tokenizer.next_token() != Token::EOF {
+        count += 1;
+    }
+    count
+}
+
+fn results(scan: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("tokenizer-{}-scan.txt", scan))
+}
+
+// the MB/s of each input of an earlier run
+fn read_results(scan: &str) -> Vec<(String, f64)> {
+    let text = fs::read_to_string(results(scan)).unwrap_or_default();
+    text.lines()
+        .filter_map(|line| {
+            let (name, speed) = line.split_once(' ')?;
+            Some((name.to_string(), speed.parse().ok()?))
+        })
+        .collect()
+}
+
+fn main() {
+    let (scan, other) = if cfg!(feature = "byte-scan") {
+        ("byte", "word")
+    } else {
+        ("word", "byte")
+    };
+    let others = read_results(other);
+    let mut saved = String::new();
+    println!("{}-at-a-time scan", scan);
+    for (name, input) in inputs() {
+        let mut best = Duration::MAX;
+        let mut tokens = 0;
+        for _ in 0..RUNS {
+            let start = Instant::now();
+            tokens = tokenize(&input);
+            best = best.min(start.elapsed());
+        }
+        let mb = input.len() as f64 / (1024.0 * 1024.0);
+        let speed = mb / best.as_secs_f64();
+        let compared = match others.iter().find(|(other_name, _)| other_name == name) {
+            Some((_, other_speed)) => format!(
+                ", {:>8.1} MB/s {}-at-a-time ({:.2}x)",
+                other_speed,
+                other,
+                speed / other_speed
+            ),
+            None => String::new(),
+        };
+        println!(
+            "{:<12} {:>6.2} MB {:>9} tokens {:>9.2?} {:>8.1} MB/s{}",
+            name, mb, tokens, best, speed, compared
+        );
+        saved.push_str(&format!("{} {}\n", name, speed));
+    }
+    fs::write(results(scan), saved).expect("can't save the results");
+    if others.is_empty() {
+        println!("run the {}-at-a-time scan to compare", other);
+    }
+}
@@ -0,0 +1,795 @@
+use super::{needs_separator, TokenKind};
+use crate::parser::node::*;
+use crate::source_map::source_map::{Mapping, SourceMap};
+use crate::tokenizer::source_location::{ColumnEncoding, LineIndex, Position};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GeneratorMode {
+    /// no whitespace except where tokens would otherwise merge
+    Compact,
+    /// one rule or declaration per line, nested blocks are indented
+    Pretty,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BracePlacement {
+    /// `a {`
+    SameLine,
+    /// `a` and `{` on the next line
+    NextLine,
+}
+
+pub struct GeneratorOptions {
+    pub mode: GeneratorMode,
+    /// indentation of one nesting level in pretty mode
+    pub indent: String,
+    /// line break in pretty mode, e.g. `\r\n`
+    pub newline: String,
+    pub brace: BracePlacement,
+    /// write the comments attached to nodes
+    pub comments: bool,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        GeneratorOptions {
+            mode: GeneratorMode::Compact,
+            indent: "  ".to_string(),
+            newline: "\n".to_string(),
+            brace: BracePlacement::SameLine,
+            comments: false,
+        }
+    }
+}
+
+/// what the source map of `generate_with_source_map` refers to
+pub struct SourceMapOptions {
+    /// the name of the generated file
+    pub file: Option<String>,
+    /// the name of the parsed file
+    pub source: String,
+    /// the parsed text, embedded as `sourcesContent`
+    ///
+    /// source map columns count UTF-16 code units, they are taken from this text,
+    /// without it the columns of the nodes are used so parse with `ColumnEncoding::Utf16`
+    pub source_content: Option<String>,
+    /// the source map of the parsed file when it was generated by another tool,
+    /// mappings are chained through it to the original sources
+    pub input: Option<SourceMap>,
+}
+
+impl Default for SourceMapOptions {
+    fn default() -> Self {
+        SourceMapOptions {
+            file: None,
+            source: "<unknown>".to_string(),
+            source_content: None,
+            input: None,
+        }
+    }
+}
+
+pub struct Generator<'o> {
+    options: &'o GeneratorOptions,
+    output: String,
+    depth: usize,
+    // kind of the last token written
+    last: TokenKind,
+    // a whitespace node was skipped, it is written before the next token if it's significant
+    pending_space: bool,
+    map: Option<SourceMap>,
+    map_options: Option<&'o SourceMapOptions>,
+    // the lines of `source_content` with UTF-16 columns
+    source_lines: Option<LineIndex<'o>>,
+    // zero-based position in the output in UTF-16 code units, only tracked for the source map
+    line: usize,
+    column: usize,
+    // the start of the node whose first token is written next
+    pending_mapping: Option<Position>,
+}
+
+/// generate CSS text from a node
+pub fn generate(node: &Node, options: &GeneratorOptions) -> String {
+    let mut generator = Generator::new(options);
+    generator.generate(node);
+    generator.output
+}
+
+/// generate CSS text and a source map pointing every node back to its location
+pub fn generate_with_source_map(
+    node: &Node,
+    options: &GeneratorOptions,
+    map_options: &SourceMapOptions,
+) -> (String, SourceMap) {
+    let mut generator = Generator::new(options);
+    let mut map = SourceMap::new();
+    map.file = map_options.file.clone();
+    generator.map = Some(map);
+    generator.map_options = Some(map_options);
+    generator.source_lines = map_options
+        .source_content
+        .as_deref()
+        .map(|text| LineIndex::with_column_encoding(text, ColumnEncoding::Utf16));
+    generator.generate(node);
+    let map = generator.map.take().unwrap_or_default();
+    (generator.output, map)
+}
+
+impl<'o> Generator<'o> {
+    pub fn new(options: &'o GeneratorOptions) -> Generator<'o> {
+        Generator {
+            options,
+            output: String::new(),
+            depth: 0,
+            last: TokenKind::WhiteSpace,
+            pending_space: false,
+            map: None,
+            map_options: None,
+            source_lines: None,
+            line: 0,
+            column: 0,
+            pending_mapping: None,
+        }
+    }
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+    pub fn into_output(self) -> String {
+        self.output
+    }
+    #[inline]
+    fn is_pretty(&self) -> bool {
+        self.options.mode == GeneratorMode::Pretty
+    }
+    // write a chunk starting with a `first` token and ending with a `last` token
+    fn chunk(&mut self, first: TokenKind, text: &str, last: TokenKind) {
+        if text.is_empty() {
+            return;
+        }
+        let space = if std::mem::take(&mut self.pending_space) {
+            !is_space_insignificant(self.last, first)
+        } else {
+            false
+        };
+        if space || needs_separator(self.last, first) {
+            self.write(" ");
+        }
+        if let Some(start) = self.pending_mapping.take() {
+            self.add_mapping(start);
+        }
+        self.write(text);
+        self.last = last;
+    }
+    fn write(&mut self, text: &str) {
+        self.output.push_str(text);
+        if self.map.is_none() {
+            return;
+        }
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\n' || (c == '\r' && chars.peek() != Some(&'\n')) {
+                self.line += 1;
+                self.column = 0;
+            } else {
+                self.column += c.len_utf16();
+            }
+        }
+    }
+    fn add_mapping(&mut self, start: Position) {
+        let (map, map_options) = match (self.map.as_mut(), self.map_options) {
+            (Some(map), Some(map_options)) => (map, map_options),
+            _ => return,
+        };
+        let start = match &self.source_lines {
+            Some(lines) => lines.position(start.offset),
+            None => start,
+        };
+        let input = map_options.input.as_ref();
+        let original = input.and_then(|input| Some((input, input.original_position(&start)?)));
+        let (source, line, column) = match original {
+            Some((input, original)) => {
+                let source = map.add_source(
+                    &input.sources[original.source],
+                    input.sources_content[original.source].as_deref(),
+                );
+                (source, original.line, original.column)
+            }
+            // a position the input map doesn't cover points to the parsed file
+            None => {
+                let source =
+                    map.add_source(&map_options.source, map_options.source_content.as_deref());
+                (source, start.line, start.column)
+            }
+        };
+        map.add_mapping(Mapping {
+            generated_line: self.line,
+            generated_column: self.column,
+            source,
+            original_line: line.saturating_sub(1),
+            original_column: column.saturating_sub(1),
+        });
+    }
+    #[inline]
+    fn token(&mut self, kind: TokenKind, text: &str) {
+        self.chunk(kind, text, kind);
+    }
+    #[inline]
+    fn delim(&mut self, c: char) {
+        let mut buf = [0; 4];
+        self.token(TokenKind::Delim(c), c.encode_utf8(&mut buf));
+    }
+    // a space that is only written in pretty mode
+    fn pretty_space(&mut self) {
+        if self.is_pretty() {
+            self.space();
+        }
+    }
+    fn space(&mut self) {
+        self.pending_space = false;
+        self.write(" ");
+        self.last = TokenKind::WhiteSpace;
+    }
+    fn newline(&mut self) {
+        self.pending_space = false;
+        let options = self.options;
+        self.write(&options.newline);
+        for _ in 0..self.depth {
+            self.write(&options.indent);
+        }
+        self.last = TokenKind::WhiteSpace;
+    }
+    fn raw(&mut self, text: &str) {
+        let (first, last) = edge_kinds(text);
+        self.chunk(first, text, last);
+    }
+    fn comment(&mut self, comment: &Comment) {
+        let text = format!("/*{}*/", comment.value);
+        self.chunk(TokenKind::Other, &text, TokenKind::Other);
+    }
+    // comments on their own lines in pretty mode
+    fn comments(&mut self, comments: &[Comment]) {
+        if !self.options.comments {
+            return;
+        }
+        for comment in comments {
+            if self.is_pretty() && !self.output.is_empty() {
+                self.newline();
+            }
+            self.comment(comment);
+        }
+    }
+    fn trailing_comments(&mut self, node: &Node) {
+        if !self.options.comments {
+            return;
+        }
+        for comment in node.trailing_comments() {
+            self.pretty_space();
+            self.comment(comment);
+        }
+    }
+    pub fn generate(&mut self, node: &Node) {
+        if self.map.is_some() && !matches!(node, Node::WhiteSpace(_) | Node::Comment(_)) {
+            self.pending_mapping = Some(node.loc().start);
+        }
+        match node {
+            Node::StyleSheet(node) => {
+                self.statements(&node.children, false);
+                self.comments(&node.comments.inner);
+            }
+            Node::Rule(rule) => {
+                self.generate(&rule.prelude);
+                self.generate(&rule.block);
+            }
+            Node::AtRule(at_rule) => {
+                self.token(TokenKind::AtKeyword, &format!("@{}", at_rule.name));
+                if let Some(prelude) = &at_rule.prelude {
+                    self.pretty_space();
+                    self.generate(prelude);
+                }
+                if let Some(block) = &at_rule.block {
+                    self.generate(block);
+                }
+            }
+            Node::Block(block) => {
+                if block.children.iter().all(is_statement) {
+                    self.statement_block(&block.children, &block.comments.inner);
+                } else {
+                    // the block of an unknown at-rule holds component values
+                    self.pretty_space();
+                    self.delim('{');
+                    self.children(&block.children);
+                    self.delim('}');
+                }
+            }
+            Node::DeclarationList(list) => {
+                self.statements(&list.children, true);
+                self.comments(&list.comments.inner);
+            }
+            Node::Declaration(declaration) => {
+                self.token(TokenKind::Ident, &declaration.property);
+                self.token(TokenKind::Colon, ":");
+                self.pretty_space();
+                self.generate(&declaration.value);
+                if declaration.important {
+                    self.pretty_space();
+                    self.delim('!');
+                    self.token(TokenKind::Ident, "important");
+                }
+            }
+            Node::AtRulePrelude(AtRulePrelude { children, .. })
+            | Node::Value(Value { children, .. })
+            | Node::Selector(Selector { children, .. })
+            | Node::MediaQuery(MediaQuery { children, .. }) => self.children(children),
+            Node::SelectorList(SelectorList { children, .. })
+            | Node::MediaQueryList(MediaQueryList { children, .. }) => {
+                for (i, child) in children.iter().enumerate() {
+                    if i > 0 {
+                        self.token(TokenKind::Comma, ",");
+                        self.pretty_space();
+                    }
+                    self.generate(child);
+                }
+            }
+            Node::TypeSelector(selector) => self.raw(&selector.name),
+            Node::IdSelector(selector) => {
+                self.token(TokenKind::Hash, &format!("#{}", selector.name))
+            }
+            Node::ClassSelector(selector) => {
+                self.delim('.');
+                self.token(TokenKind::Ident, &selector.name);
+            }
+            Node::AttributeSelector(attribute) => {
+                self.delim('[');
+                self.generate(&attribute.name);
+                if let Some(matcher) = &attribute.matcher {
+                    self.raw(matcher);
+                }
+                if let Some(value) = &attribute.value {
+                    self.generate(value);
+                }
+                if let Some(flags) = &attribute.flags {
+                    self.space();
+                    self.token(TokenKind::Ident, flags);
+                }
+                self.delim(']');
+            }
+            Node::PseudoClassSelector(PseudoClassSelector { name, children, .. })
+            | Node::PseudoElementSelector(PseudoElementSelector { name, children, .. }) => {
+                self.token(TokenKind::Colon, ":");
+                if let Node::PseudoElementSelector(_) = node {
+                    self.token(TokenKind::Colon, ":");
+                }
+                match children {
+                    Some(children) => {
+                        self.token(TokenKind::Function, &format!("{}(", name));
+                        self.children(children);
+                        self.token(TokenKind::RightParenthesis, ")");
+                    }
+                    None => self.token(TokenKind::Ident, name),
+                }
+            }
+            Node::Combinator(combinator) => {
+                if combinator.name == " " {
+                    self.space();
+                } else {
+                    self.pretty_space();
+                    self.raw(&combinator.name);
+                    self.pretty_space();
+                }
+            }
+            Node::Nth(nth) => {
+                self.generate(&nth.nth);
+                if let Some(selector) = &nth.selector {
+                    self.space();
+                    self.token(TokenKind::Ident, "of");
+                    self.space();
+                    self.generate(selector);
+                }
+            }
+            Node::AnPlusB(an_plus_b) => self.raw(&an_plus_b_text(an_plus_b)),
+            Node::MediaFeature(feature) => {
+                self.token(TokenKind::LeftParenthesis, "(");
+                self.token(TokenKind::Ident, &feature.name);
+                if let Some(value) = &feature.value {
+                    self.token(TokenKind::Colon, ":");
+                    self.pretty_space();
+                    self.generate(value);
+                }
+                self.token(TokenKind::RightParenthesis, ")");
+            }
+            Node::Ratio(ratio) => {
+                self.token(TokenKind::Number, &ratio.left);
+                self.pretty_space();
+                self.delim('/');
+                self.pretty_space();
+                self.token(TokenKind::Number, &ratio.right);
+            }
+            Node::Identifier(identifier) => self.token(TokenKind::Ident, &identifier.name),
+            Node::Number(number) => self.token(TokenKind::Number, &number.value),
+            Node::Dimension(dimension) => self.token(
+                TokenKind::Dimension,
+                &format!("{}{}", dimension.value, dimension.unit),
+            ),
+            Node::Percentage(percentage) => {
+                self.token(TokenKind::Percentage, &format!("{}%", percentage.value))
+            }
+            Node::String(string) => self.token(TokenKind::Other, &string.value),
+            Node::Url(url) => {
+                self.token(TokenKind::Url, "url(");
+                match &*url.value {
+                    Node::Raw(raw) => self.chunk(TokenKind::Other, &raw.value, TokenKind::Other),
+                    value => self.generate(value),
+                }
+                self.token(TokenKind::RightParenthesis, ")");
+            }
+            Node::Hash(hash) => self.token(TokenKind::Hash, &format!("#{}", hash.value)),
+            Node::Function(function) => {
+                self.token(TokenKind::Function, &format!("{}(", function.name));
+                self.children(&function.children);
+                self.token(TokenKind::RightParenthesis, ")");
+            }
+            Node::Parentheses(parentheses) => {
+                self.token(TokenKind::LeftParenthesis, "(");
+                self.children(&parentheses.children);
+                self.token(TokenKind::RightParenthesis, ")");
+            }
+            Node::Brackets(brackets) => {
+                self.delim('[');
+                self.children(&brackets.children);
+                self.delim(']');
+            }
+            Node::Operator(operator) => match &*operator.value {
+                "," => self.token(TokenKind::Comma, ","),
+                ":" => self.token(TokenKind::Colon, ":"),
+                value => self.raw(value),
+            },
+            Node::UnicodeRange(range) => self.token(TokenKind::Ident, &range.value),
+            Node::WhiteSpace(_) => self.pending_space = true,
+            Node::Comment(comment) => {
+                if self.options.comments {
+                    self.comment(comment);
+                }
+            }
+            Node::Raw(raw) => self.raw(&raw.value),
+            Node::CDO(_) => self.token(TokenKind::Other, "<!--"),
+            Node::CDC(_) => self.token(TokenKind::CDC, "-->"),
+        }
+    }
+    fn children(&mut self, children: &[Node]) {
+        for child in children {
+            self.generate(child);
+        }
+        // whitespace at the end of a list is never significant
+        self.pending_space = false;
+    }
+    // rules, at-rules and declarations, `in_block` is false at the top level of a stylesheet
+    fn statements(&mut self, children: &[Node], in_block: bool) {
+        for (i, child) in children.iter().enumerate() {
+            if self.is_pretty() && (i > 0 || self.depth > 0) {
+                self.newline();
+            }
+            if self.options.comments {
+                for comment in child.leading_comments() {
+                    self.comment(comment);
+                    if self.is_pretty() {
+                        self.newline();
+                    }
+                }
+            }
+            self.generate(child);
+            let semicolon = match child {
+                Node::Declaration(_) => self.is_pretty() || i + 1 < children.len(),
+                Node::AtRule(at_rule) => at_rule.block.is_none(),
+                Node::Raw(_) => in_block && (self.is_pretty() || i + 1 < children.len()),
+                _ => false,
+            };
+            if semicolon {
+                self.token(TokenKind::Other, ";");
+            }
+            self.trailing_comments(child);
+        }
+    }
+    fn statement_block(&mut self, children: &[Node], inner_comments: &[Comment]) {
+        let has_inner = self.options.comments && !inner_comments.is_empty();
+        if self.is_pretty() {
+            match self.options.brace {
+                BracePlacement::SameLine => self.space(),
+                BracePlacement::NextLine => self.newline(),
+            }
+        }
+        self.delim('{');
+        if children.is_empty() && !has_inner {
+            self.delim('}');
+            return;
+        }
+        self.depth += 1;
+        self.statements(children, true);
+        if has_inner {
+            for comment in inner_comments {
+                if self.is_pretty() {
+                    self.newline();
+                }
+                self.comment(comment);
+            }
+        }
+        self.depth -= 1;
+        if self.is_pretty() {
+            self.newline();
+        }
+        self.delim('}');
+    }
+}
+
+#[inline]
+fn is_statement(node: &Node) -> bool {
+    matches!(
+        node,
+        Node::Rule(_) | Node::AtRule(_) | Node::Declaration(_) | Node::Raw(_)
+    )
+}
+
+// whitespace next to these tokens can be dropped without changing the meaning,
+// whitespace around `+` and `-` is kept for `calc()`
+fn is_space_insignificant(prev: TokenKind, next: TokenKind) -> bool {
+    use TokenKind::*;
+    matches!(
+        prev,
+        WhiteSpace | Comma | Colon | Function | Url | LeftParenthesis | Delim('/') | Delim('*')
+    ) || matches!(
+        next,
+        Comma | RightParenthesis | Delim('/') | Delim('*') | Delim('!')
+    )
+}
+
+// the kinds of the first and the last token of a raw text
+fn edge_kinds(text: &str) -> (TokenKind, TokenKind) {
+    let kind = |c: char| {
+        if c.is_ascii_digit() {
+            TokenKind::Number
+        } else if c.is_ascii_alphabetic() || c == '_' || !c.is_ascii() {
+            TokenKind::Ident
+        } else if c == '-' || c == '\\' {
+            // could start or end an identifier
+            TokenKind::Ident
+        } else if c == '(' {
+            TokenKind::LeftParenthesis
+        } else if c == ')' {
+            TokenKind::RightParenthesis
+        } else if c == ',' {
+            TokenKind::Comma
+        } else if c.is_ascii_whitespace() {
+            TokenKind::WhiteSpace
+        } else {
+            TokenKind::Delim(c)
+        }
+    };
+    let first = text.chars().next().map_or(TokenKind::Other, kind);
+    let last = text
+        .chars()
+        .last()
+        .map_or(TokenKind::Other, |c| match kind(c) {
+            // `1` at the end of `a1` or `1px` is a part of an identifier
+            TokenKind::Number => TokenKind::Ident,
+            kind => kind,
+        });
+    (first, last)
+}
+
+// https://drafts.csswg.org/css-syntax-3/#serializing-anb
+fn an_plus_b_text(an_plus_b: &AnPlusB) -> String {
+    let mut text = String::new();
+    if let Some(a) = &an_plus_b.a {
+        match &**a {
+            "1" | "+1" => text.push('n'),
+            "-1" => text.push_str("-n"),
+            a => {
+                text.push_str(a);
+                text.push('n');
+            }
+        }
+    }
+    if let Some(b) = &an_plus_b.b {
+        if an_plus_b.a.is_some() && !b.starts_with('-') && !b.starts_with('+') {
+            text.push('+');
+        }
+        text.push_str(b);
+    }
+    if text.is_empty() {
+        text.push('0');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parser::{Parser, ParserContext, ParserOptions};
+
+    fn compact(input: &str) -> String {
+        let node = Parser::new(input, ParserOptions::default()).parse().node;
+        generate(&node, &GeneratorOptions::default())
+    }
+
+    fn pretty(input: &str, options: GeneratorOptions) -> String {
+        let node = Parser::new(input, ParserOptions::default()).parse().node;
+        let options = GeneratorOptions {
+            mode: GeneratorMode::Pretty,
+            ..options
+        };
+        generate(&node, &options)
+    }
+
+    #[test]
+    fn generate_compact() {
+        assert_eq!(
+            compact("a , b > c  .d { color : red ; margin: 0 auto !important; }"),
+            "a,b>c .d{color:red;margin:0 auto!important}"
+        );
+        assert_eq!(
+            compact("@import url( 'a.css' ) screen;\n@media screen and (min-width: 100px) { a { width: calc(100% - 10px) } }"),
+            "@import url('a.css') screen;@media screen and (min-width:100px){a{width:calc(100% - 10px)}}"
+        );
+        assert_eq!(
+            compact("a:not( .b , c )::before, li:nth-child( 2n + 1 ), [x|='y' i] { font: 12px / 1.5 a , b }"),
+            "a:not(.b,c)::before,li:nth-child(2n+1),[x|='y' i]{font:12px/1.5 a,b}"
+        );
+        // an escaped space before `)` is part of the url
+        assert_eq!(compact("a{b:url( a\\  )}"), "a{b:url(a\\ )}");
+    }
+
+    #[test]
+    fn separate_merging_tokens() {
+        let value = Parser::new(
+            "1px/**/solid/**/-2 a/**/(b)",
+            ParserOptions {
+                context: ParserContext::Value,
+                keep_comments: false,
+                ..Default::default()
+            },
+        )
+        .parse()
+        .node;
+        assert_eq!(
+            generate(&value, &GeneratorOptions::default()),
+            "1px solid -2 a (b)"
+        );
+    }
+
+    #[test]
+    fn generate_pretty() {
+        let input = "a,b{color:red;margin:0}@media print{a{b:c}}@font-face{}";
+        assert_eq!(
+            pretty(input, GeneratorOptions::default()),
+            "a, b {\n  color: red;\n  margin: 0;\n}\n@media print {\n  a {\n    b: c;\n  }\n}\n@font-face {}"
+        );
+        let options = GeneratorOptions {
+            indent: "\t".to_string(),
+            newline: "\r\n".to_string(),
+            brace: BracePlacement::NextLine,
+            ..Default::default()
+        };
+        assert_eq!(pretty("a{b:c}", options), "a\r\n{\r\n\tb: c;\r\n}");
+    }
+
+    #[test]
+    fn generate_source_map() {
+        let input = "a {\n  color: red;\n}\n@media print {\n  b { top: 0 }\n}";
+        let node = Parser::new(input, ParserOptions::default()).parse().node;
+        let map_options = SourceMapOptions {
+            file: Some("out.css".to_string()),
+            source: "in.css".to_string(),
+            source_content: Some(input.to_string()),
+            input: None,
+        };
+        let (css, map) =
+            generate_with_source_map(&node, &GeneratorOptions::default(), &map_options);
+        assert_eq!(css, "a{color:red}@media print{b{top:0}}");
+        let segments: Vec<(usize, usize, usize)> = map
+            .mappings
+            .iter()
+            .map(|m| (m.generated_column, m.original_line, m.original_column))
+            .collect();
+        assert_eq!(
+            segments,
+            vec![
+                (0, 0, 0),
+                (1, 0, 2),
+                (2, 1, 2),
+                (8, 1, 9),
+                (12, 3, 0),
+                (19, 3, 7),
+                (24, 3, 13),
+                (25, 4, 2),
+                (26, 4, 4),
+                (27, 4, 6),
+                (31, 4, 11)
+            ]
+        );
+        let json = map.to_json();
+        assert!(json.starts_with(
+            r#"{"version":3,"file":"out.css","sources":["in.css"],"sourcesContent":["a {\n"#
+        ));
+        assert!(json
+            .ends_with(r#""mappings":"AAAA,CAAE,CACA,MAAO,IAET,OAAO,KAAM,CACX,CAAE,CAAE,IAAK"}"#));
+    }
+
+    #[test]
+    fn count_source_map_columns_in_utf16() {
+        let input = "/* 😀 */ a { content: '😀' } b {}";
+        let node = Parser::new(input, ParserOptions::default()).parse().node;
+        let map_options = SourceMapOptions {
+            source_content: Some(input.to_string()),
+            ..Default::default()
+        };
+        let (css, map) =
+            generate_with_source_map(&node, &GeneratorOptions::default(), &map_options);
+        assert_eq!(css, "a{content:'😀'}b{}");
+        let segments: Vec<(usize, usize)> = map
+            .mappings
+            .iter()
+            .map(|m| (m.generated_column, m.original_column))
+            .collect();
+        assert_eq!(
+            segments,
+            vec![(0, 9), (1, 11), (2, 13), (10, 22), (15, 29), (16, 31)]
+        );
+    }
+
+    #[test]
+    fn chain_input_source_map() {
+        use crate::source_map::decode_data_url;
+        use crate::source_map::source_map::OriginalPosition;
+
+        // `.a { .b { color: red; } }` compiled by a preprocessor
+        let input = ".a .b{color:red}\n/*# sourceMappingURL=data:application/json;base64,eyJ2ZXJzaW9uIjozLCJzb3VyY2VzIjpbImEuc2NzcyJdLCJzb3VyY2VzQ29udGVudCI6WyIuYSB7XG4gIC5iIHsgY29sb3I6IHJlZDsgfVxufSJdLCJuYW1lcyI6W10sIm1hcHBpbmdzIjoiQUFDRSxHQUFHLEVBQUUsQ0FBQSxLQUFLIn0= */";
+        let result = Parser::new(input, ParserOptions::default()).parse();
+        let url = result.source_mapping_url.unwrap();
+        let input_map = SourceMap::from_json(&decode_data_url(url).unwrap()).unwrap();
+        assert_eq!(
+            input_map.original_position(&Position {
+                line: 1,
+                column: 13,
+                offset: 12
+            }),
+            Some(OriginalPosition {
+                source: 0,
+                line: 2,
+                column: 13
+            })
+        );
+
+        let map_options = SourceMapOptions {
+            input: Some(input_map),
+            ..Default::default()
+        };
+        let (css, map) =
+            generate_with_source_map(&result.node, &GeneratorOptions::default(), &map_options);
+        assert_eq!(css, ".a .b{color:red}");
+        assert_eq!(map.sources, vec!["a.scss"]);
+        assert_eq!(
+            map.sources_content,
+            vec![Some(".a {\n  .b { color: red; }\n}".to_string())]
+        );
+        let segments: Vec<(usize, usize, usize)> = map
+            .mappings
+            .iter()
+            .map(|m| (m.generated_column, m.original_line, m.original_column))
+            .collect();
+        assert_eq!(
+            segments,
+            vec![(0, 1, 2), (3, 1, 5), (5, 1, 7), (6, 1, 7), (12, 1, 12)]
+        );
+    }
+
+    #[test]
+    fn generate_comments() {
+        let options = GeneratorOptions {
+            comments: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            pretty("/* a */\na { b: c; /* d */ }", options),
+            "/* a */\na {\n  b: c; /* d */\n}"
+        );
+    }
+}
@@ -0,0 +1,50 @@
+#[allow(clippy::module_inception)]
+pub mod generator;
+pub mod serializer;
+
+// the kind of the token at the edge of a generated chunk, see `needs_separator`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TokenKind {
+    Ident,
+    Function,
+    AtKeyword,
+    Hash,
+    Url,
+    BadUrl,
+    Number,
+    Percentage,
+    Dimension,
+    CDC,
+    LeftParenthesis,
+    RightParenthesis,
+    Comma,
+    Colon,
+    Delim(char),
+    WhiteSpace,
+    Other,
+}
+
+// https://drafts.csswg.org/css-syntax-3/#serialization
+// two tokens written next to each other would be read back differently,
+// e.g. two idents merge to one and `1` followed by `px` becomes a dimension
+pub fn needs_separator(prev: TokenKind, next: TokenKind) -> bool {
+    use TokenKind::*;
+    let ident_like = matches!(
+        next,
+        Ident | Function | Url | BadUrl | Delim('-') | Number | Percentage | Dimension
+    );
+    match prev {
+        // `a (` would become a function
+        Ident => ident_like || matches!(next, CDC | LeftParenthesis),
+        AtKeyword | Hash | Dimension => ident_like || next == CDC,
+        Delim('#') | Delim('-') => ident_like,
+        Number => matches!(
+            next,
+            Ident | Function | Url | BadUrl | Number | Percentage | Dimension | Delim('%')
+        ),
+        Delim('@') => matches!(next, Ident | Function | Url | BadUrl | Delim('-')),
+        Delim('.') | Delim('+') => matches!(next, Number | Percentage | Dimension),
+        Delim('/') => next == Delim('*'),
+        _ => false,
+    }
+}
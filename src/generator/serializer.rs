@@ -0,0 +1,104 @@
+use super::{needs_separator, TokenKind};
+use crate::tokenizer::token::Token;
+use std::borrow::Cow;
+
+// https://drafts.csswg.org/css-syntax-3/#serialization
+/// write tokens back as CSS text, an empty comment goes between two tokens that would
+/// otherwise be read back as other tokens, like `a` and `b` or `1` and `px`
+pub fn serialize(tokens: &[Token]) -> String {
+    let mut output = String::new();
+    let mut last = TokenKind::WhiteSpace;
+    for token in tokens {
+        let kind = token_kind(token);
+        if needs_separator(last, kind) {
+            output.push_str("/**/");
+        }
+        output.push_str(&token_text(token));
+        last = kind;
+    }
+    output
+}
+
+fn token_kind(token: &Token) -> TokenKind {
+    match token {
+        Token::Ident(_) => TokenKind::Ident,
+        Token::Function(_) => TokenKind::Function,
+        Token::AtKeyword(_) => TokenKind::AtKeyword,
+        Token::Hash(..) => TokenKind::Hash,
+        Token::Url(_) => TokenKind::Url,
+        Token::BadUrl(_) => TokenKind::BadUrl,
+        Token::Number(_) => TokenKind::Number,
+        Token::Percentage(_) => TokenKind::Percentage,
+        Token::Dimension(_) => TokenKind::Dimension,
+        Token::CDC => TokenKind::CDC,
+        Token::LeftParenthesis => TokenKind::LeftParenthesis,
+        Token::RightParenthesis => TokenKind::RightParenthesis,
+        Token::Comma => TokenKind::Comma,
+        Token::Colon => TokenKind::Colon,
+        Token::Delim(s) => s.chars().next().map_or(TokenKind::Other, TokenKind::Delim),
+        Token::WhiteSpace(_) => TokenKind::WhiteSpace,
+        _ => TokenKind::Other,
+    }
+}
+
+// the source text of a token, the tokenizer keeps the text of most tokens as they were written
+fn token_text<'a>(token: &Token<'a>) -> Cow<'a, str> {
+    match *token {
+        Token::Function(name) => Cow::Owned(format!("{}(", name)),
+        Token::Hash(name, _) => Cow::Owned(format!("#{}", name)),
+        Token::Url(url) => Cow::Owned(format!("url({})", url)),
+        // the remnants of a bad url end with its `)`
+        Token::BadUrl(url) => Cow::Owned(format!("url({}", url)),
+        // a lone backslash is only read back as a delim before a newline
+        Token::Delim("\\") => Cow::Borrowed("\\\n"),
+        Token::Ident(s)
+        | Token::AtKeyword(s)
+        | Token::String(s)
+        | Token::BadString(s)
+        | Token::Delim(s)
+        | Token::WhiteSpace(s)
+        | Token::Comment(s) => Cow::Borrowed(s),
+        Token::Number(numeric) | Token::Percentage(numeric) | Token::Dimension(numeric) => {
+            Cow::Borrowed(numeric.repr)
+        }
+        Token::UnicodeRange(range) => Cow::Borrowed(range.value),
+        _ => token.value(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::tokenizer::Tokenizer;
+
+    // the tokens read back from the output are the same
+    fn round_trip(input: &str) -> String {
+        let tokens: Vec<Token> = Tokenizer::new(input)
+            .filter(|token| !matches!(token, Token::WhiteSpace(_)))
+            .collect();
+        let output = serialize(&tokens);
+        let read_back: Vec<Token> = Tokenizer::new(&output)
+            .filter(|token| !matches!(token, Token::Comment("/**/")))
+            .collect();
+        assert_eq!(read_back, tokens, "{}", output);
+        output
+    }
+
+    #[test]
+    fn separate_tokens() {
+        assert_eq!(round_trip("a b"), "a/**/b");
+        assert_eq!(round_trip("1 px"), "1/**/px");
+        assert_eq!(round_trip("1 2% 3px"), "1/**/2%3px");
+        assert_eq!(round_trip("#a -b"), "#a/**/-b");
+        assert_eq!(round_trip("@m - - >"), "@m/**/-/**/->");
+        assert_eq!(round_trip(". 5 + 5"), "./**/5+/**/5");
+        assert_eq!(round_trip("/ *"), "//**/*");
+        assert_eq!(round_trip("a ( 1 %"), "a/**/(1/**/%");
+        assert_eq!(round_trip("@a ( #a ( 1px ("), "@a(#a(1px(");
+        assert_eq!(round_trip("url( b ) a url(c d)"), "url(b)a/**/url(c d)");
+        assert_eq!(round_trip("a: b, c ;"), "a:b,c;");
+        assert_eq!(round_trip("f( 'x' ) [ i ]"), "f('x')[i]");
+        assert_eq!(round_trip("a /* b */ c"), "a/* b */c");
+        assert_eq!(serialize(&[Token::Delim("\\"), Token::Ident("a")]), "\\\na");
+    }
+}
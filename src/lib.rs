@@ -1,6 +1,13 @@
+pub mod tokenizer;
+pub mod parser;
+pub mod generator;
+pub mod source_map;
+pub mod walker;
+
 #[cfg(test)]
 mod tests {
     #[test]
+    #[allow(redundant_semicolons, unused_variables)]
     fn it_works() {
        enum A {
             String
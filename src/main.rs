@@ -1,23 +1,14 @@
-mod tokenizer;
-mod parser;
-
 use std::time::Instant;
-// use tokenizer::token::Token;
-use parser::parser::Parser;
-use parser::parser::ParserOptions;
-use parser::parser::ParserContext;
+// use csstree::tokenizer::token::Token;
+use csstree::parser::parser::Parser;
+use csstree::parser::parser::ParserOptions;
+use csstree::parser::parser::ParserContext;
 
 
 // fn token_by_csstree(content: &str) {
-//     let mut instance = Tokenizer::new(content);
-//     // let mut arr = vec![];
-//     loop {
-//         let token = instance.next_token();
-//         if let Token::EOF = token {
-//             break;
-//         } else {
-//             // arr.push(token);
-//         }
+//     let instance = Tokenizer::new(content);
+//     // let arr: Vec<Token> = instance.collect();
+//     for token in instance {
 //         // println!("{:?}", token);
 //     }
 // }
@@ -45,9 +36,11 @@ fn main() {
     let start = Instant::now();
     // token_by_csstree(&content);
     let options = ParserOptions {
-        context: ParserContext::Stylesheet
+        context: ParserContext::Stylesheet,
+        ..Default::default()
     };
-    let mut instance = Parser::new(&content, options);
+    let mut instance = Parser::new(content, options);
+    let result = instance.parse();
 
     // let mut a = vec![1];
     // println!("{:?}", a.len());
@@ -64,5 +57,10 @@ fn main() {
 
     let duration = start.elapsed();
 
+    println!("{:#?}", result.node);
+    for error in result.errors {
+        println!("{}", error);
+    }
+
     println!("Time elapsed in expensive_function() is: {:?}", duration);
 }
@@ -0,0 +1,234 @@
+use crate::parser::node::{
+    Brackets, Comment, Comments, Dimension, Function, Hash, Identifier, Node, Number, Operator,
+    Parentheses, Percentage, Raw, Str, UnicodeRange, Url, WhiteSpace, CDC, CDO,
+};
+use crate::tokenizer::source_location::SourceLocation;
+use crate::tokenizer::token::Token;
+use std::borrow::Cow;
+
+// https://drafts.csswg.org/css-syntax-3/#component-value
+// a preserved token, or a simple block / function with the opening token in `token`
+pub(crate) struct ComponentValue<'a> {
+    pub token: Token<'a>,
+    pub loc: SourceLocation,
+    pub children: Vec<ComponentValue<'a>>,
+    // comments before the component value
+    pub comments: Vec<Comment<'a>>,
+    // comments before the closing token of a simple block or function
+    pub inner_comments: Vec<Comment<'a>>,
+}
+
+impl<'a> ComponentValue<'a> {
+    pub fn new(token: Token<'a>, loc: SourceLocation, comments: Vec<Comment<'a>>) -> Self {
+        ComponentValue {
+            token,
+            loc,
+            children: vec![],
+            comments,
+            inner_comments: vec![],
+        }
+    }
+    #[inline]
+    pub fn is_whitespace(&self) -> bool {
+        matches!(self.token, Token::WhiteSpace(_))
+    }
+    #[inline]
+    pub fn is_delim(&self, delim: &str) -> bool {
+        matches!(self.token, Token::Delim(d) if d == delim)
+    }
+    #[inline]
+    pub fn is_ident(&self, name: &str) -> bool {
+        matches!(self.token, Token::Ident(s) if s.eq_ignore_ascii_case(name))
+    }
+}
+
+pub(crate) fn trim_end_whitespace(values: &mut Vec<ComponentValue>) {
+    while values.last().is_some_and(|v| v.is_whitespace()) {
+        values.pop();
+    }
+}
+
+pub(crate) fn trim_whitespace(mut values: Vec<ComponentValue>) -> Vec<ComponentValue> {
+    trim_end_whitespace(&mut values);
+    let from = values.iter().take_while(|v| v.is_whitespace()).count();
+    values.split_off(from)
+}
+
+pub(crate) fn trim_whitespace_slice<'b, 'a>(
+    values: &'b [ComponentValue<'a>],
+) -> &'b [ComponentValue<'a>] {
+    let from = values.iter().take_while(|v| v.is_whitespace()).count();
+    let to = values.len()
+        - values[from..]
+            .iter()
+            .rev()
+            .take_while(|v| v.is_whitespace())
+            .count();
+    &values[from..to]
+}
+
+// location covering a list of component values
+pub(crate) fn loc_of(values: &[ComponentValue], default: SourceLocation) -> SourceLocation {
+    match (values.first(), values.last()) {
+        (Some(first), Some(last)) => SourceLocation {
+            start: first.loc.start,
+            end: last.loc.end,
+        },
+        _ => default,
+    }
+}
+
+pub(crate) fn raw<'a>(input: &'a str, loc: SourceLocation) -> Node<'a> {
+    Node::Raw(Raw {
+        value: Cow::Borrowed(&input[loc.start.offset..loc.end.offset]),
+        comments: Comments::default(),
+        loc,
+    })
+}
+
+pub(crate) fn comment_nodes<'a>(comments: Vec<Comment<'a>>) -> impl Iterator<Item = Node<'a>> {
+    comments.into_iter().map(Node::Comment)
+}
+
+// the comments inside a simple block or function, for nodes that don't keep its children
+pub(crate) fn nested_comments<'a>(value: &ComponentValue<'a>) -> Vec<Comment<'a>> {
+    let mut comments = vec![];
+    for child in &value.children {
+        comments.extend(child.comments.iter().cloned());
+        comments.extend(nested_comments(child));
+    }
+    comments.extend(value.inner_comments.iter().cloned());
+    comments
+}
+
+// the parts of a comma separated list, each with the comments before the comma after it
+pub(crate) fn split_commas<'b, 'a>(
+    values: &'b [ComponentValue<'a>],
+) -> impl Iterator<Item = (&'b [ComponentValue<'a>], &'b [Comment<'a>])> {
+    let commas = values
+        .iter()
+        .filter(|v| v.token == Token::Comma)
+        .map(|v| v.comments.as_slice());
+    values
+        .split(|v| v.token == Token::Comma)
+        .zip(commas.chain(std::iter::once(&[][..])))
+}
+
+// lower component values to value nodes, comments are kept as Comment nodes
+pub(crate) fn to_nodes<'a>(values: Vec<ComponentValue<'a>>, input: &'a str) -> Vec<Node<'a>> {
+    let mut nodes = Vec::with_capacity(values.len());
+    for mut value in values {
+        nodes.extend(comment_nodes(std::mem::take(&mut value.comments)));
+        nodes.push(to_node(value, input));
+    }
+    nodes
+}
+
+fn to_children<'a>(value: ComponentValue<'a>, input: &'a str) -> Vec<Node<'a>> {
+    let mut children = to_nodes(value.children, input);
+    children.extend(comment_nodes(value.inner_comments));
+    children
+}
+
+pub(crate) fn to_node<'a>(value: ComponentValue<'a>, input: &'a str) -> Node<'a> {
+    let loc = value.loc;
+    match value.token {
+        Token::Ident(name) => Node::Identifier(Identifier {
+            name: Cow::Borrowed(name),
+            loc,
+        }),
+        Token::Function(name) if name.eq_ignore_ascii_case("url") => {
+            let args = trim_whitespace_slice(&value.children);
+            if let [arg] = args {
+                if let Token::String(s) = arg.token {
+                    let string = Node::String(Str {
+                        value: Cow::Borrowed(s),
+                        loc: arg.loc,
+                    });
+                    return Node::Url(Url {
+                        value: Box::new(string),
+                        loc,
+                    });
+                }
+            }
+            Node::Function(Function {
+                name: Cow::Borrowed(name),
+                children: to_children(value, input),
+                loc,
+            })
+        }
+        Token::Function(name) => Node::Function(Function {
+            name: Cow::Borrowed(name),
+            children: to_children(value, input),
+            loc,
+        }),
+        Token::LeftParenthesis => Node::Parentheses(Parentheses {
+            children: to_children(value, input),
+            loc,
+        }),
+        Token::LeftSquareBracket => Node::Brackets(Brackets {
+            children: to_children(value, input),
+            loc,
+        }),
+        Token::Hash(name, _) => Node::Hash(Hash {
+            value: Cow::Borrowed(name),
+            loc,
+        }),
+        Token::String(s) => Node::String(Str {
+            value: Cow::Borrowed(s),
+            loc,
+        }),
+        Token::Url(s) => {
+            let raw = Node::Raw(Raw {
+                value: Cow::Borrowed(s),
+                comments: Comments::default(),
+                loc: value.children.first().map_or(loc, |v| v.loc),
+            });
+            Node::Url(Url {
+                value: Box::new(raw),
+                loc,
+            })
+        }
+        Token::Number(numeric) => Node::Number(Number {
+            value: Cow::Borrowed(numeric.repr),
+            loc,
+        }),
+        Token::Percentage(numeric) => Node::Percentage(Percentage {
+            value: Cow::Borrowed(numeric.number),
+            loc,
+        }),
+        Token::Dimension(numeric) => Node::Dimension(Dimension {
+            value: Cow::Borrowed(numeric.number),
+            unit: Cow::Borrowed(numeric.unit),
+            loc,
+        }),
+        Token::WhiteSpace(s) => Node::WhiteSpace(WhiteSpace {
+            value: Cow::Borrowed(s),
+            loc,
+        }),
+        Token::Delim(s) => Node::Operator(Operator {
+            value: Cow::Borrowed(s),
+            loc,
+        }),
+        Token::Comma => Node::Operator(Operator {
+            value: Cow::Borrowed(","),
+            loc,
+        }),
+        Token::Colon => Node::Operator(Operator {
+            value: Cow::Borrowed(":"),
+            loc,
+        }),
+        Token::Semicolon => Node::Operator(Operator {
+            value: Cow::Borrowed(";"),
+            loc,
+        }),
+        Token::UnicodeRange(range) => Node::UnicodeRange(UnicodeRange {
+            value: Cow::Borrowed(range.value),
+            loc,
+        }),
+        Token::CDO => Node::CDO(CDO { loc }),
+        Token::CDC => Node::CDC(CDC { loc }),
+        // `{}` blocks, bad strings and urls, at-keywords and unmatched closing tokens
+        _ => raw(input, loc),
+    }
+}
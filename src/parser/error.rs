@@ -0,0 +1,36 @@
+use crate::tokenizer::source_location::SourceLocation;
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ParseErrorKind {
+    UnexpectedEOF,
+    UnexpectedToken,
+    /// an entry point for one rule or declaration got only whitespace and comments
+    EmptyInput,
+    /// an entry point for one rule or declaration got more after it
+    ExtraInput,
+    ColonExpected,
+    IdentifierExpected,
+    SelectorExpected,
+    InvalidUnicodeRange,
+}
+
+// https://drafts.csswg.org/css-syntax-3/#parse-error
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub message: String,
+    pub loc: SourceLocation,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}:{})",
+            self.message, self.loc.start.line, self.loc.start.column
+        )
+    }
+}
+
+pub type ParseErrorCallback = Box<dyn FnMut(&ParseError)>;
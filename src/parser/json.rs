@@ -0,0 +1,919 @@
+use crate::parser::node::*;
+use crate::tokenizer::source_location::{ColumnEncoding, LineIndex, Position, SourceLocation};
+use std::borrow::Cow;
+use std::fmt;
+
+// a JSON value, object keys keep their order
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct JsonError {
+    pub message: String,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+fn error<T>(message: String) -> Result<T, JsonError> {
+    Err(JsonError { message })
+}
+
+impl JsonValue {
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+    pub fn stringify(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out);
+        out
+    }
+    fn write(&self, out: &mut String) {
+        match self {
+            JsonValue::Null => out.push_str("null"),
+            JsonValue::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            JsonValue::Number(n) => {
+                if n.fract() == 0.0 && n.abs() < 1e15 {
+                    out.push_str(&(*n as i64).to_string());
+                } else if n.is_finite() {
+                    out.push_str(&n.to_string());
+                } else {
+                    out.push_str("null");
+                }
+            }
+            JsonValue::String(s) => write_string(s, out),
+            JsonValue::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    item.write(out);
+                }
+                out.push(']');
+            }
+            JsonValue::Object(fields) => {
+                out.push('{');
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    write_string(key, out);
+                    out.push(':');
+                    value.write(out);
+                }
+                out.push('}');
+            }
+        }
+    }
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// https://www.json.org/
+pub fn parse_json(input: &str) -> Result<JsonValue, JsonError> {
+    let mut reader = JsonReader {
+        input: input.as_bytes(),
+        position: 0,
+    };
+    let value = reader.read_value()?;
+    reader.skip_whitespace();
+    if reader.position < reader.input.len() {
+        return reader.unexpected();
+    }
+    Ok(value)
+}
+
+struct JsonReader<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl<'a> JsonReader<'a> {
+    fn unexpected<T>(&self) -> Result<T, JsonError> {
+        if self.position >= self.input.len() {
+            return error("Unexpected end of JSON input".to_string());
+        }
+        error(format!(
+            "Unexpected character in JSON at position {}",
+            self.position
+        ))
+    }
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.position).copied()
+    }
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.position += 1;
+        }
+    }
+    fn expect(&mut self, byte: u8) -> Result<(), JsonError> {
+        if self.peek() != Some(byte) {
+            return self.unexpected();
+        }
+        self.position += 1;
+        Ok(())
+    }
+    fn read_keyword(&mut self, keyword: &str, value: JsonValue) -> Result<JsonValue, JsonError> {
+        if !self.input[self.position..].starts_with(keyword.as_bytes()) {
+            return self.unexpected();
+        }
+        self.position += keyword.len();
+        Ok(value)
+    }
+    fn read_value(&mut self) -> Result<JsonValue, JsonError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.read_object(),
+            Some(b'[') => self.read_array(),
+            Some(b'"') => Ok(JsonValue::String(self.read_string()?)),
+            Some(b'-' | b'0'..=b'9') => self.read_number(),
+            Some(b't') => self.read_keyword("true", JsonValue::Bool(true)),
+            Some(b'f') => self.read_keyword("false", JsonValue::Bool(false)),
+            Some(b'n') => self.read_keyword("null", JsonValue::Null),
+            _ => self.unexpected(),
+        }
+    }
+    fn read_object(&mut self) -> Result<JsonValue, JsonError> {
+        self.expect(b'{')?;
+        let mut fields = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Ok(JsonValue::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.read_string()?;
+            self.skip_whitespace();
+            self.expect(b':')?;
+            let value = self.read_value()?;
+            fields.push((key, value));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b'}') => {
+                    self.position += 1;
+                    return Ok(JsonValue::Object(fields));
+                }
+                _ => return self.unexpected(),
+            }
+        }
+    }
+    fn read_array(&mut self) -> Result<JsonValue, JsonError> {
+        self.expect(b'[')?;
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.read_value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    return Ok(JsonValue::Array(items));
+                }
+                _ => return self.unexpected(),
+            }
+        }
+    }
+    fn read_number(&mut self) -> Result<JsonValue, JsonError> {
+        let start = self.position;
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') = self.peek() {
+            self.position += 1;
+        }
+        let text = std::str::from_utf8(&self.input[start..self.position]).unwrap();
+        match text.parse::<f64>() {
+            Ok(n) => Ok(JsonValue::Number(n)),
+            Err(_) => error(format!("Invalid number in JSON at position {}", start)),
+        }
+    }
+    fn read_hex4(&mut self) -> Result<u32, JsonError> {
+        let hex = self.input.get(self.position..self.position + 4);
+        let code = hex
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok());
+        match code {
+            Some(code) => {
+                self.position += 4;
+                Ok(code)
+            }
+            None => self.unexpected(),
+        }
+    }
+    fn read_string(&mut self) -> Result<String, JsonError> {
+        self.expect(b'"')?;
+        let mut bytes = vec![];
+        loop {
+            let byte = match self.peek() {
+                Some(byte) => byte,
+                None => return self.unexpected(),
+            };
+            self.position += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = match self.peek() {
+                        Some(byte) => byte,
+                        None => return self.unexpected(),
+                    };
+                    self.position += 1;
+                    let c = match escaped {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let mut code = self.read_hex4()?;
+                            // a surrogate pair
+                            if (0xD800..0xDC00).contains(&code)
+                                && self.input[self.position..].starts_with(b"\\u")
+                            {
+                                self.position += 2;
+                                let low = self.read_hex4()?;
+                                code = 0x10000
+                                    + ((code - 0xD800) << 10)
+                                    + (low.wrapping_sub(0xDC00) & 0x3FF);
+                            }
+                            char::from_u32(code).unwrap_or('\u{FFFD}')
+                        }
+                        _ => {
+                            self.position -= 1;
+                            return self.unexpected();
+                        }
+                    };
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+                _ => bytes.push(byte),
+            }
+        }
+        // the input is a `&str` and escapes are encoded above, so the bytes are valid UTF-8
+        Ok(String::from_utf8(bytes).unwrap())
+    }
+}
+
+/// how node locations are written to and read from JSON
+///
+/// like in csstree, offsets and columns count UTF-16 code units of the input as JavaScript
+/// strings do, so `input` must be the text the nodes were parsed from
+pub struct JsonOptions<'s> {
+    pub input: &'s str,
+    /// the `source` of every location, the `filename` option of the csstree parser
+    pub source: &'s str,
+    /// the column encoding of the nodes read from JSON, the `column_encoding` of the parser
+    pub column_encoding: ColumnEncoding,
+}
+
+impl<'s> JsonOptions<'s> {
+    pub fn new(input: &'s str) -> JsonOptions<'s> {
+        JsonOptions {
+            input,
+            source: "<unknown>",
+            column_encoding: ColumnEncoding::default(),
+        }
+    }
+}
+
+// converts positions between byte offsets and the UTF-16 offsets of JavaScript
+struct Locations<'s> {
+    source: &'s str,
+    utf16_lines: LineIndex<'s>,
+    lines: LineIndex<'s>,
+    // the end of every non-ASCII character with the number of bytes more than UTF-16 code
+    // units before it
+    extra_bytes: Vec<(usize, usize)>,
+}
+
+impl<'s> Locations<'s> {
+    fn new(options: &JsonOptions<'s>) -> Locations<'s> {
+        let mut extra_bytes = vec![];
+        let mut extra = 0;
+        for (i, c) in options.input.char_indices() {
+            if !c.is_ascii() {
+                extra += c.len_utf8() - c.len_utf16();
+                extra_bytes.push((i + c.len_utf8(), extra));
+            }
+        }
+        Locations {
+            source: options.source,
+            utf16_lines: LineIndex::with_column_encoding(options.input, ColumnEncoding::Utf16),
+            lines: LineIndex::with_column_encoding(options.input, options.column_encoding),
+            extra_bytes,
+        }
+    }
+    fn utf16_position(&self, position: &Position) -> Position {
+        let position = self.utf16_lines.position(position.offset);
+        let before = self
+            .extra_bytes
+            .partition_point(|&(end, _)| end <= position.offset);
+        let extra = before.checked_sub(1).map_or(0, |i| self.extra_bytes[i].1);
+        Position {
+            offset: position.offset - extra,
+            ..position
+        }
+    }
+    fn byte_position(&self, offset: usize) -> Position {
+        let before = self
+            .extra_bytes
+            .partition_point(|&(end, extra)| end - extra <= offset);
+        let extra = before.checked_sub(1).map_or(0, |i| self.extra_bytes[i].1);
+        self.lines.position(offset + extra)
+    }
+}
+
+// https://github.com/csstree/csstree/blob/master/docs/utils.md#toplainobjectast
+/// convert a node to the JSON shape of `csstree.toPlainObject`
+pub fn to_plain_object(node: &Node, options: &JsonOptions) -> JsonValue {
+    Locations::new(options).object(node)
+}
+
+/// serialize a node to JSON in the shape of `csstree.toPlainObject`
+pub fn to_json(node: &Node, options: &JsonOptions) -> String {
+    to_plain_object(node, options).stringify()
+}
+
+#[inline]
+fn string(s: &str) -> JsonValue {
+    JsonValue::String(s.to_string())
+}
+
+fn optional_string(s: &Option<Cow<str>>) -> JsonValue {
+    s.as_deref().map_or(JsonValue::Null, string)
+}
+
+impl<'s> Locations<'s> {
+    fn object(&self, node: &Node) -> JsonValue {
+        let fields = match node {
+            Node::Rule(rule) => vec![
+                ("prelude", self.object(&rule.prelude)),
+                ("block", self.object(&rule.block)),
+            ],
+            Node::AtRule(at_rule) => vec![
+                ("name", string(&at_rule.name)),
+                ("prelude", self.optional_node(&at_rule.prelude)),
+                ("block", self.optional_node(&at_rule.block)),
+            ],
+            Node::Declaration(declaration) => vec![
+                ("important", JsonValue::Bool(declaration.important)),
+                ("property", string(&declaration.property)),
+                ("value", self.object(&declaration.value)),
+            ],
+            Node::TypeSelector(TypeSelector { name, .. })
+            | Node::IdSelector(IdSelector { name, .. })
+            | Node::ClassSelector(ClassSelector { name, .. })
+            | Node::Combinator(Combinator { name, .. })
+            | Node::Identifier(Identifier { name, .. }) => vec![("name", string(name))],
+            Node::AttributeSelector(attribute) => vec![
+                ("name", self.object(&attribute.name)),
+                ("matcher", optional_string(&attribute.matcher)),
+                ("value", self.optional_node(&attribute.value)),
+                ("flags", optional_string(&attribute.flags)),
+            ],
+            Node::PseudoClassSelector(PseudoClassSelector { name, children, .. })
+            | Node::PseudoElementSelector(PseudoElementSelector { name, children, .. }) => vec![
+                ("name", string(name)),
+                (
+                    "children",
+                    children.as_ref().map_or(JsonValue::Null, |c| self.list(c)),
+                ),
+            ],
+            Node::Nth(nth) => vec![
+                ("nth", self.object(&nth.nth)),
+                ("selector", self.optional_node(&nth.selector)),
+            ],
+            Node::AnPlusB(an_plus_b) => vec![
+                ("a", optional_string(&an_plus_b.a)),
+                ("b", optional_string(&an_plus_b.b)),
+            ],
+            Node::MediaFeature(feature) => vec![
+                ("name", string(&feature.name)),
+                ("value", self.optional_node(&feature.value)),
+            ],
+            Node::Ratio(ratio) => vec![
+                ("left", string(&ratio.left)),
+                ("right", string(&ratio.right)),
+            ],
+            Node::Dimension(dimension) => vec![
+                ("value", string(&dimension.value)),
+                ("unit", string(&dimension.unit)),
+            ],
+            Node::Number(Number { value, .. })
+            | Node::Percentage(Percentage { value, .. })
+            | Node::String(Str { value, .. })
+            | Node::Hash(Hash { value, .. })
+            | Node::Operator(Operator { value, .. })
+            | Node::UnicodeRange(UnicodeRange { value, .. })
+            | Node::WhiteSpace(WhiteSpace { value, .. })
+            | Node::Comment(Comment { value, .. })
+            | Node::Raw(Raw { value, .. }) => vec![("value", string(value))],
+            Node::Url(url) => vec![("value", self.object(&url.value))],
+            Node::Function(function) => vec![
+                ("name", string(&function.name)),
+                ("children", self.list(&function.children)),
+            ],
+            _ => match node.children() {
+                Some(children) => vec![("children", self.list(children))],
+                None => vec![],
+            },
+        };
+        let mut object = vec![
+            (
+                "type".to_string(),
+                JsonValue::String(node.type_name().to_string()),
+            ),
+            ("loc".to_string(), self.location(node.loc())),
+        ];
+        object.extend(fields.into_iter().map(|(k, v)| (k.to_string(), v)));
+        // comments are not part of the csstree AST, they are kept in an extra field
+        if let Some(comments) = node.comments() {
+            if !comments.leading.is_empty()
+                || !comments.inner.is_empty()
+                || !comments.trailing.is_empty()
+            {
+                let comments = vec![
+                    ("leading".to_string(), self.comment_list(&comments.leading)),
+                    ("inner".to_string(), self.comment_list(&comments.inner)),
+                    (
+                        "trailing".to_string(),
+                        self.comment_list(&comments.trailing),
+                    ),
+                ];
+                object.push(("comments".to_string(), JsonValue::Object(comments)));
+            }
+        }
+        JsonValue::Object(object)
+    }
+
+    fn optional_node(&self, node: &Option<Box<Node>>) -> JsonValue {
+        node.as_deref()
+            .map_or(JsonValue::Null, |node| self.object(node))
+    }
+    fn list(&self, children: &[Node]) -> JsonValue {
+        JsonValue::Array(children.iter().map(|node| self.object(node)).collect())
+    }
+    fn comment_list(&self, comments: &[Comment]) -> JsonValue {
+        let to_value = |c: &Comment| self.object(&Node::Comment(c.clone()));
+        JsonValue::Array(comments.iter().map(to_value).collect())
+    }
+    fn position(&self, position: &Position) -> JsonValue {
+        let position = self.utf16_position(position);
+        JsonValue::Object(vec![
+            (
+                "offset".to_string(),
+                JsonValue::Number(position.offset as f64),
+            ),
+            ("line".to_string(), JsonValue::Number(position.line as f64)),
+            (
+                "column".to_string(),
+                JsonValue::Number(position.column as f64),
+            ),
+        ])
+    }
+    fn location(&self, loc: &SourceLocation) -> JsonValue {
+        JsonValue::Object(vec![
+            ("source".to_string(), string(self.source)),
+            ("start".to_string(), self.position(&loc.start)),
+            ("end".to_string(), self.position(&loc.end)),
+        ])
+    }
+}
+
+// https://github.com/csstree/csstree/blob/master/docs/utils.md#fromplainobjectobject
+/// build a node from the JSON shape of `csstree.toPlainObject`, a missing `loc` is taken as 1:1
+pub fn from_plain_object(
+    value: &JsonValue,
+    options: &JsonOptions,
+) -> Result<Node<'static>, JsonError> {
+    read_node(value, &Locations::new(options))
+}
+
+fn read_node(value: &JsonValue, locations: &Locations) -> Result<Node<'static>, JsonError> {
+    let type_name = match value.get("type") {
+        Some(JsonValue::String(s)) => s.as_str(),
+        _ => return error("Node type is expected".to_string()),
+    };
+    let fields = Fields {
+        value,
+        type_name,
+        locations,
+    };
+    let loc = fields.loc()?;
+    let node = match type_name {
+        "StyleSheet" => Node::StyleSheet(StyleSheet {
+            children: fields.children("children")?,
+            comments: fields.comments()?,
+            loc,
+        }),
+        "Rule" => Node::Rule(Rule {
+            prelude: fields.node("prelude")?,
+            block: fields.node("block")?,
+            comments: fields.comments()?,
+            loc,
+        }),
+        "Atrule" => Node::AtRule(AtRule {
+            name: fields.string("name")?,
+            prelude: fields.optional_node("prelude")?,
+            block: fields.optional_node("block")?,
+            comments: fields.comments()?,
+            loc,
+        }),
+        "AtrulePrelude" => Node::AtRulePrelude(AtRulePrelude {
+            children: fields.children("children")?,
+            loc,
+        }),
+        "Block" => Node::Block(Block {
+            children: fields.children("children")?,
+            comments: fields.comments()?,
+            loc,
+        }),
+        "DeclarationList" => Node::DeclarationList(DeclarationList {
+            children: fields.children("children")?,
+            comments: fields.comments()?,
+            loc,
+        }),
+        "Declaration" => Node::Declaration(Declaration {
+            // csstree keeps a hack like `!ie` as a string
+            important: match value.get("important") {
+                Some(JsonValue::Bool(b)) => *b,
+                Some(JsonValue::String(_)) => true,
+                _ => false,
+            },
+            property: fields.string("property")?,
+            value: fields.node("value")?,
+            comments: fields.comments()?,
+            loc,
+        }),
+        "Value" => Node::Value(Value {
+            children: fields.children("children")?,
+            loc,
+        }),
+        "SelectorList" => Node::SelectorList(SelectorList {
+            children: fields.children("children")?,
+            loc,
+        }),
+        "Selector" => Node::Selector(Selector {
+            children: fields.children("children")?,
+            loc,
+        }),
+        "TypeSelector" => Node::TypeSelector(TypeSelector {
+            name: fields.string("name")?,
+            loc,
+        }),
+        "IdSelector" => Node::IdSelector(IdSelector {
+            name: fields.string("name")?,
+            loc,
+        }),
+        "ClassSelector" => Node::ClassSelector(ClassSelector {
+            name: fields.string("name")?,
+            loc,
+        }),
+        "AttributeSelector" => Node::AttributeSelector(AttributeSelector {
+            name: fields.node("name")?,
+            matcher: fields.optional_string("matcher")?,
+            value: fields.optional_node("value")?,
+            flags: fields.optional_string("flags")?,
+            loc,
+        }),
+        "PseudoClassSelector" => Node::PseudoClassSelector(PseudoClassSelector {
+            name: fields.string("name")?,
+            children: fields.optional_children("children")?,
+            loc,
+        }),
+        "PseudoElementSelector" => Node::PseudoElementSelector(PseudoElementSelector {
+            name: fields.string("name")?,
+            children: fields.optional_children("children")?,
+            loc,
+        }),
+        "Combinator" => Node::Combinator(Combinator {
+            name: fields.string("name")?,
+            loc,
+        }),
+        "Nth" => Node::Nth(Nth {
+            nth: fields.node("nth")?,
+            selector: fields.optional_node("selector")?,
+            loc,
+        }),
+        "AnPlusB" => Node::AnPlusB(AnPlusB {
+            a: fields.optional_string("a")?,
+            b: fields.optional_string("b")?,
+            loc,
+        }),
+        "MediaQueryList" => Node::MediaQueryList(MediaQueryList {
+            children: fields.children("children")?,
+            loc,
+        }),
+        "MediaQuery" => Node::MediaQuery(MediaQuery {
+            children: fields.children("children")?,
+            loc,
+        }),
+        "MediaFeature" => Node::MediaFeature(MediaFeature {
+            name: fields.string("name")?,
+            value: fields.optional_node("value")?,
+            loc,
+        }),
+        "Ratio" => Node::Ratio(Ratio {
+            left: fields.string("left")?,
+            right: fields.string("right")?,
+            loc,
+        }),
+        "Identifier" => Node::Identifier(Identifier {
+            name: fields.string("name")?,
+            loc,
+        }),
+        "Number" => Node::Number(Number {
+            value: fields.string("value")?,
+            loc,
+        }),
+        "Dimension" => Node::Dimension(Dimension {
+            value: fields.string("value")?,
+            unit: fields.string("unit")?,
+            loc,
+        }),
+        "Percentage" => Node::Percentage(Percentage {
+            value: fields.string("value")?,
+            loc,
+        }),
+        "String" => Node::String(Str {
+            value: fields.string("value")?,
+            loc,
+        }),
+        "Url" => Node::Url(Url {
+            value: fields.node("value")?,
+            loc,
+        }),
+        "Hash" => Node::Hash(Hash {
+            value: fields.string("value")?,
+            loc,
+        }),
+        "Function" => Node::Function(Function {
+            name: fields.string("name")?,
+            children: fields.children("children")?,
+            loc,
+        }),
+        "Parentheses" => Node::Parentheses(Parentheses {
+            children: fields.children("children")?,
+            loc,
+        }),
+        "Brackets" => Node::Brackets(Brackets {
+            children: fields.children("children")?,
+            loc,
+        }),
+        "Operator" => Node::Operator(Operator {
+            value: fields.string("value")?,
+            loc,
+        }),
+        "UnicodeRange" => Node::UnicodeRange(UnicodeRange {
+            value: fields.string("value")?,
+            loc,
+        }),
+        "WhiteSpace" => Node::WhiteSpace(WhiteSpace {
+            value: fields.string("value")?,
+            loc,
+        }),
+        "Comment" => Node::Comment(Comment {
+            value: fields.string("value")?,
+            loc,
+        }),
+        "Raw" => Node::Raw(Raw {
+            value: fields.string("value")?,
+            comments: fields.comments()?,
+            loc,
+        }),
+        "CDO" => Node::CDO(CDO { loc }),
+        "CDC" => Node::CDC(CDC { loc }),
+        _ => return error(format!("Unknown node type `{}`", type_name)),
+    };
+    Ok(node)
+}
+
+/// parse JSON in the shape of `csstree.toPlainObject` to a node
+pub fn from_json(input: &str, options: &JsonOptions) -> Result<Node<'static>, JsonError> {
+    from_plain_object(&parse_json(input)?, options)
+}
+
+// field access of a plain object, errors name the node type
+struct Fields<'v> {
+    value: &'v JsonValue,
+    type_name: &'v str,
+    locations: &'v Locations<'v>,
+}
+
+impl<'v> Fields<'v> {
+    fn expected<T>(&self, key: &str, what: &str) -> Result<T, JsonError> {
+        error(format!(
+            "{} is expected for `{}` of {}",
+            what, key, self.type_name
+        ))
+    }
+    fn string(&self, key: &str) -> Result<Cow<'static, str>, JsonError> {
+        match self.value.get(key) {
+            Some(JsonValue::String(s)) => Ok(Cow::Owned(s.clone())),
+            _ => self.expected(key, "A string"),
+        }
+    }
+    fn optional_string(&self, key: &str) -> Result<Option<Cow<'static, str>>, JsonError> {
+        match self.value.get(key) {
+            None | Some(JsonValue::Null) => Ok(None),
+            _ => self.string(key).map(Some),
+        }
+    }
+    fn node(&self, key: &str) -> Result<Box<Node<'static>>, JsonError> {
+        match self.value.get(key) {
+            Some(value @ JsonValue::Object(_)) => Ok(Box::new(read_node(value, self.locations)?)),
+            _ => self.expected(key, "A node"),
+        }
+    }
+    fn optional_node(&self, key: &str) -> Result<Option<Box<Node<'static>>>, JsonError> {
+        match self.value.get(key) {
+            None | Some(JsonValue::Null) => Ok(None),
+            _ => self.node(key).map(Some),
+        }
+    }
+    fn children(&self, key: &str) -> Result<Vec<Node<'static>>, JsonError> {
+        match self.value.get(key) {
+            Some(JsonValue::Array(items)) => items
+                .iter()
+                .map(|item| read_node(item, self.locations))
+                .collect(),
+            _ => self.expected(key, "An array"),
+        }
+    }
+    fn optional_children(&self, key: &str) -> Result<Option<Vec<Node<'static>>>, JsonError> {
+        match self.value.get(key) {
+            None | Some(JsonValue::Null) => Ok(None),
+            _ => self.children(key).map(Some),
+        }
+    }
+    fn loc(&self) -> Result<SourceLocation, JsonError> {
+        let loc = match self.value.get("loc") {
+            None | Some(JsonValue::Null) => {
+                let start = Position {
+                    offset: 0,
+                    line: 1,
+                    column: 1,
+                };
+                return Ok(SourceLocation { start, end: start });
+            }
+            Some(loc) => loc,
+        };
+        let position = |key: &str| -> Result<Position, JsonError> {
+            let value = loc.get(key);
+            let number = |name: &str| match value.and_then(|v| v.get(name)) {
+                Some(JsonValue::Number(n)) if *n >= 0.0 => Ok(*n as usize),
+                _ => self.expected("loc", "A position"),
+            };
+            // only the offset is read, the line and column are taken from the input
+            Ok(self.locations.byte_position(number("offset")?))
+        };
+        Ok(SourceLocation {
+            start: position("start")?,
+            end: position("end")?,
+        })
+    }
+    fn comments(&self) -> Result<Comments<'static>, JsonError> {
+        let comments = match self.value.get("comments") {
+            None | Some(JsonValue::Null) => return Ok(Comments::default()),
+            Some(comments) => Fields {
+                value: comments,
+                type_name: self.type_name,
+                locations: self.locations,
+            },
+        };
+        let list = |key: &str| -> Result<Vec<Comment<'static>>, JsonError> {
+            let nodes = comments.optional_children(key)?.unwrap_or_default();
+            nodes
+                .into_iter()
+                .map(|node| match node {
+                    Node::Comment(comment) => Ok(comment),
+                    _ => comments.expected(key, "A comment"),
+                })
+                .collect()
+        };
+        Ok(Comments {
+            leading: list("leading")?,
+            inner: list("inner")?,
+            trailing: list("trailing")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parser::{Parser, ParserOptions};
+
+    #[test]
+    fn serialize_plain_object() {
+        let node = Parser::new("a{color:red}", ParserOptions::default())
+            .parse()
+            .node;
+        let json = to_json(&node, &JsonOptions::new("a{color:red}"));
+        assert!(json.starts_with(
+            r#"{"type":"StyleSheet","loc":{"source":"<unknown>","start":{"offset":0,"line":1,"column":1},"end":{"offset":12,"line":1,"column":13}},"children":[{"type":"Rule""#
+        ));
+        assert!(json.contains(r#""type":"Declaration","#));
+        assert!(json.contains(r#""important":false,"property":"color","value":{"type":"Value""#));
+        assert!(json.contains(r#"{"type":"Identifier","loc":{"source":"<unknown>","start":{"offset":8,"line":1,"column":9},"end":{"offset":11,"line":1,"column":12}},"name":"red"}"#));
+    }
+
+    #[test]
+    fn round_trip() {
+        let input = "/* x */ @media (min-width: 1px) { a > b::before, :nth-child(2n+1) { c: \"\\\"q\" 1px !important } }\n@x;";
+        let node = Parser::new(input, ParserOptions::default()).parse().node;
+        let options = JsonOptions::new(input);
+        let parsed = from_json(&to_json(&node, &options), &options).unwrap();
+        assert_eq!(parsed, node);
+    }
+
+    #[test]
+    fn count_locations_in_utf16() {
+        let input = "/* 😀 */ a::after { content: 'é😀' }\n😀 {}";
+        let node = Parser::new(input, ParserOptions::default()).parse().node;
+        let options = JsonOptions {
+            source: "a.css",
+            ..JsonOptions::new(input)
+        };
+        let object = to_plain_object(&node, &options);
+        let rules = match object.get("children") {
+            Some(JsonValue::Array(rules)) => rules,
+            _ => panic!("expected children"),
+        };
+        let comment = &rules[0].get("comments").unwrap().get("leading").unwrap();
+        assert_eq!(
+            comment.stringify(),
+            r#"[{"type":"Comment","loc":{"source":"a.css","start":{"offset":0,"line":1,"column":1},"end":{"offset":8,"line":1,"column":9}},"value":" 😀 "}]"#
+        );
+        let end = rules[0].get("loc").unwrap().get("end").unwrap();
+        assert_eq!(end.stringify(), r#"{"offset":36,"line":1,"column":37}"#);
+        let start = rules[1].get("loc").unwrap().get("start").unwrap();
+        assert_eq!(start.stringify(), r#"{"offset":37,"line":2,"column":1}"#);
+        let parsed = from_plain_object(&object, &options).unwrap();
+        assert_eq!(parsed, node);
+    }
+
+    #[test]
+    fn parse_json_values() {
+        let value =
+            parse_json(r#" {"a": [1, -2.5e1, true, null], "b": "é\ud83d\ude00\n"} "#).unwrap();
+        assert_eq!(
+            value.get("a"),
+            Some(&JsonValue::Array(vec![
+                JsonValue::Number(1.0),
+                JsonValue::Number(-25.0),
+                JsonValue::Bool(true),
+                JsonValue::Null
+            ]))
+        );
+        assert_eq!(
+            value.get("b"),
+            Some(&JsonValue::String("é😀\n".to_string()))
+        );
+        assert!(parse_json("[1,]").is_err());
+        assert!(parse_json("{} x").is_err());
+    }
+
+    #[test]
+    fn reject_bad_nodes() {
+        let options = JsonOptions::new("");
+        let err = from_json(r#"{"type":"Rule","prelude":null}"#, &options).unwrap_err();
+        assert_eq!(err.to_string(), "A node is expected for `prelude` of Rule");
+        let node = from_json(r#"{"type":"Identifier","loc":null,"name":"a"}"#, &options).unwrap();
+        assert_eq!(node.loc().start.line, 1);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::parser::component::{
+    comment_nodes, loc_of, nested_comments, to_node, trim_whitespace_slice, ComponentValue,
+};
+use crate::parser::node::{Identifier, MediaFeature, MediaQuery, MediaQueryList, Node, Ratio};
+use crate::tokenizer::source_location::SourceLocation;
+use crate::tokenizer::token::Token;
+use std::borrow::Cow;
+
+// https://drafts.csswg.org/mediaqueries-4/#mq-syntax
+// `screen and (min-width: 100px), print`
+pub(crate) fn parse_media_query_list<'a>(
+    values: Vec<ComponentValue<'a>>,
+    loc: SourceLocation,
+    input: &'a str,
+) -> Node<'a> {
+    let mut children = vec![];
+    let mut query = vec![];
+    for value in values {
+        if value.token == Token::Comma {
+            // the comments before a comma stay with the query before it
+            let mut node = parse_media_query(std::mem::take(&mut query), input);
+            if let Some(children) = node.as_mut().and_then(|node| node.children_mut()) {
+                children.extend(comment_nodes(value.comments));
+            }
+            children.extend(node);
+            continue;
+        }
+        query.push(value);
+    }
+    children.extend(parse_media_query(query, input));
+    Node::MediaQueryList(MediaQueryList { children, loc })
+}
+
+fn parse_media_query<'a>(values: Vec<ComponentValue<'a>>, input: &'a str) -> Option<Node<'a>> {
+    let loc = loc_of(trim_whitespace_slice(&values), values.first()?.loc);
+    let mut children: Vec<Node<'a>> = vec![];
+    for mut v in values.into_iter().filter(|v| !v.is_whitespace()) {
+        children.extend(comment_nodes(std::mem::take(&mut v.comments)));
+        let node = match v.token {
+            Token::Ident(name) => Node::Identifier(Identifier {
+                name: Cow::Borrowed(name),
+                loc: v.loc,
+            }),
+            Token::LeftParenthesis => match parse_media_feature(&v) {
+                Some(feature) => {
+                    children.extend(comment_nodes(nested_comments(&v)));
+                    feature
+                }
+                None => to_node(v, input),
+            },
+            _ => to_node(v, input),
+        };
+        children.push(node);
+    }
+    if children.is_empty() {
+        return None;
+    }
+    Some(Node::MediaQuery(MediaQuery { children, loc }))
+}
+
+// `(color)`, `(min-width: 100px)`, `(aspect-ratio: 16 / 9)`
+fn parse_media_feature<'a>(block: &ComponentValue<'a>) -> Option<Node<'a>> {
+    let values: Vec<&ComponentValue<'a>> = block
+        .children
+        .iter()
+        .filter(|v| !v.is_whitespace())
+        .collect();
+    let name = match values.first()?.token {
+        Token::Ident(name) => Cow::Borrowed(name),
+        _ => return None,
+    };
+    let value = match &values[1..] {
+        [] => None,
+        [colon, value] if colon.token == Token::Colon => match value.token {
+            Token::Number(_) | Token::Dimension(_) | Token::Ident(_) => {
+                Some(to_node(clone_token(value), ""))
+            }
+            _ => return None,
+        },
+        [colon, left, slash, right] if colon.token == Token::Colon && slash.is_delim("/") => {
+            match (left.token, right.token) {
+                (Token::Number(l), Token::Number(r)) => Some(Node::Ratio(Ratio {
+                    left: Cow::Borrowed(l.repr),
+                    right: Cow::Borrowed(r.repr),
+                    loc: SourceLocation {
+                        start: left.loc.start,
+                        end: right.loc.end,
+                    },
+                })),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    Some(Node::MediaFeature(MediaFeature {
+        name,
+        value: value.map(Box::new),
+        loc: block.loc,
+    }))
+}
+
+#[inline]
+fn clone_token<'a>(value: &ComponentValue<'a>) -> ComponentValue<'a> {
+    ComponentValue::new(value.token, value.loc, vec![])
+}
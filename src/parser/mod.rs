@@ -1,9 +1,15 @@
+mod component;
+pub mod error;
+pub mod json;
+mod media;
 pub mod node;
+#[allow(clippy::module_inception)]
 pub mod parser;
+mod selector;
 use crate::tokenizer::token::Token;
 
 // If the current input token is a <{-token>, <[-token>, or <(-token>,
-pub fn is_block_start(token: Token) -> bool {
+pub fn is_block_start(token: &Token) -> bool {
     if let Token::LeftCurlyBracket = token {
         return true;
     }
@@ -18,7 +24,7 @@ pub fn is_block_start(token: Token) -> bool {
 
 // The ending token is the mirror variant of the current input token.
 // (E.g. if it was called with <[-token>, the ending token is <]-token>.)
-pub fn is_block_matched(start: Token, end: Token) -> bool {
+pub fn is_block_matched(start: &Token, end: &Token) -> bool {
     if let Token::LeftCurlyBracket = start {
         if let Token::RightCurlyBracket = end {
             return true;
@@ -1,36 +1,522 @@
-use crate::tokenizer::token::Token;
 use crate::tokenizer::source_location::SourceLocation;
+use std::borrow::Cow;
 
-pub struct Node<'a> {
-    pub r#type: NodeType<'a>,
+// node types follow the AST of the JS csstree, https://github.com/csstree/csstree/blob/master/docs/ast.md
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node<'a> {
+    StyleSheet(StyleSheet<'a>),
+    Rule(Rule<'a>),
+    AtRule(AtRule<'a>),
+    AtRulePrelude(AtRulePrelude<'a>),
+    Block(Block<'a>),
+    DeclarationList(DeclarationList<'a>),
+    Declaration(Declaration<'a>),
+    Value(Value<'a>),
+    SelectorList(SelectorList<'a>),
+    Selector(Selector<'a>),
+    TypeSelector(TypeSelector<'a>),
+    IdSelector(IdSelector<'a>),
+    ClassSelector(ClassSelector<'a>),
+    AttributeSelector(AttributeSelector<'a>),
+    PseudoClassSelector(PseudoClassSelector<'a>),
+    PseudoElementSelector(PseudoElementSelector<'a>),
+    Combinator(Combinator<'a>),
+    Nth(Nth<'a>),
+    AnPlusB(AnPlusB<'a>),
+    MediaQueryList(MediaQueryList<'a>),
+    MediaQuery(MediaQuery<'a>),
+    MediaFeature(MediaFeature<'a>),
+    Ratio(Ratio<'a>),
+    Identifier(Identifier<'a>),
+    Number(Number<'a>),
+    Dimension(Dimension<'a>),
+    Percentage(Percentage<'a>),
+    String(Str<'a>),
+    Url(Url<'a>),
+    Hash(Hash<'a>),
+    Function(Function<'a>),
+    Parentheses(Parentheses<'a>),
+    Brackets(Brackets<'a>),
+    Operator(Operator<'a>),
+    UnicodeRange(UnicodeRange<'a>),
+    WhiteSpace(WhiteSpace<'a>),
+    Comment(Comment<'a>),
+    Raw(Raw<'a>),
+    CDO(CDO),
+    CDC(CDC),
+}
+
+/// a comment, `value` is the text between `/*` and `*/`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment<'a> {
+    pub r#value: Cow<'a, str>,
     pub loc: SourceLocation,
-    pub children: Vec<Box<Node<'a>>>,
-    leading_comments: Vec<Comment<'a>>,
-    inner_comments: Vec<Comment<'a>>,
-    trailing_comments: Vec<Comment<'a>>,
 }
 
+/// comments attached to a rule, an at-rule, a declaration or a block
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Comments<'a> {
+    /// comments before the node
+    pub leading: Vec<Comment<'a>>,
+    /// comments inside the node that don't belong to any child, e.g. in an empty block
+    pub inner: Vec<Comment<'a>>,
+    /// comments after the node, starting on the line where the node ends
+    pub trailing: Vec<Comment<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleSheet<'a> {
+    pub children: Vec<Node<'a>>,
+    pub comments: Comments<'a>,
+    pub loc: SourceLocation,
+}
+
+// https://drafts.csswg.org/css-syntax-3/#qualified-rule
+// the prelude is a SelectorList, or Raw when it isn't a valid selector list, the block is a Block
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule<'a> {
+    pub prelude: Box<Node<'a>>,
+    pub block: Box<Node<'a>>,
+    pub comments: Comments<'a>,
+    pub loc: SourceLocation,
+}
+
+// https://drafts.csswg.org/css-syntax-3/#at-rule
+// the prelude is an AtRulePrelude, the block is a Block
+#[derive(Debug, Clone, PartialEq)]
 pub struct AtRule<'a> {
-    name: &'a str,
-    prelude: Box<Node<'a>>,
-    block: Box<Node<'a>>,
+    pub name: Cow<'a, str>,
+    pub prelude: Option<Box<Node<'a>>>,
+    pub block: Option<Box<Node<'a>>>,
+    pub comments: Comments<'a>,
+    pub loc: SourceLocation,
 }
 
-pub struct Comment<'a> {
-    pub r#value: &'a str,
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtRulePrelude<'a> {
+    pub children: Vec<Node<'a>>,
+    pub loc: SourceLocation,
+}
+
+// the `{}` block of a rule, holds rules, at-rules and declarations
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block<'a> {
+    pub children: Vec<Node<'a>>,
+    pub comments: Comments<'a>,
     pub loc: SourceLocation,
 }
 
-pub struct Block<T> {
-    children: Vec<T>,
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeclarationList<'a> {
+    pub children: Vec<Node<'a>>,
+    pub comments: Comments<'a>,
+    pub loc: SourceLocation,
 }
 
-pub enum NodeType<'a> {
-    StyleSheet,
-    AtRulePrelude,
-    Function(&'a str),
-    Block,
-    AtRule(AtRule<'a>),
-    CDC,
-    CDO,
+// https://drafts.csswg.org/css-syntax-3/#declaration
+// the value is a Value, or Raw for custom properties
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration<'a> {
+    pub property: Cow<'a, str>,
+    pub important: bool,
+    pub value: Box<Node<'a>>,
+    pub comments: Comments<'a>,
+    pub loc: SourceLocation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Value<'a> {
+    pub children: Vec<Node<'a>>,
+    pub loc: SourceLocation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectorList<'a> {
+    pub children: Vec<Node<'a>>,
+    pub loc: SourceLocation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector<'a> {
+    pub children: Vec<Node<'a>>,
+    pub loc: SourceLocation,
+}
+
+// `a`, `*`, `svg|a`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeSelector<'a> {
+    pub name: Cow<'a, str>,
+    pub loc: SourceLocation,
+}
+
+// `#name`, the name is stored without `#`
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdSelector<'a> {
+    pub name: Cow<'a, str>,
+    pub loc: SourceLocation,
+}
+
+// `.name`, the name is stored without `.`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassSelector<'a> {
+    pub name: Cow<'a, str>,
+    pub loc: SourceLocation,
+}
+
+// `[name]`, `[name="value" i]`, the name is an Identifier, the value is a String or an Identifier
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeSelector<'a> {
+    pub name: Box<Node<'a>>,
+    pub matcher: Option<Cow<'a, str>>,
+    pub value: Option<Box<Node<'a>>>,
+    pub flags: Option<Cow<'a, str>>,
+    pub loc: SourceLocation,
+}
+
+// `:hover`, `:not(a, b)`, children is `None` when there are no parentheses
+#[derive(Debug, Clone, PartialEq)]
+pub struct PseudoClassSelector<'a> {
+    pub name: Cow<'a, str>,
+    pub children: Option<Vec<Node<'a>>>,
+    pub loc: SourceLocation,
+}
+
+// `::before`, `::slotted(a)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PseudoElementSelector<'a> {
+    pub name: Cow<'a, str>,
+    pub children: Option<Vec<Node<'a>>>,
+    pub loc: SourceLocation,
+}
+
+// `>`, `+`, `~` or ` ` for descendants
+#[derive(Debug, Clone, PartialEq)]
+pub struct Combinator<'a> {
+    pub name: Cow<'a, str>,
+    pub loc: SourceLocation,
+}
+
+// the argument of `:nth-child(2n+1 of a)`, nth is an AnPlusB or an Identifier (`odd`, `even`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Nth<'a> {
+    pub nth: Box<Node<'a>>,
+    pub selector: Option<Box<Node<'a>>>,
+    pub loc: SourceLocation,
+}
+
+// https://drafts.csswg.org/css-syntax-3/#anb-microsyntax
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnPlusB<'a> {
+    pub a: Option<Cow<'a, str>>,
+    pub b: Option<Cow<'a, str>>,
+    pub loc: SourceLocation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQueryList<'a> {
+    pub children: Vec<Node<'a>>,
+    pub loc: SourceLocation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQuery<'a> {
+    pub children: Vec<Node<'a>>,
+    pub loc: SourceLocation,
+}
+
+// `(color)`, `(min-width: 100px)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaFeature<'a> {
+    pub name: Cow<'a, str>,
+    pub value: Option<Box<Node<'a>>>,
+    pub loc: SourceLocation,
+}
+
+// `16/9`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ratio<'a> {
+    pub left: Cow<'a, str>,
+    pub right: Cow<'a, str>,
+    pub loc: SourceLocation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identifier<'a> {
+    pub name: Cow<'a, str>,
+    pub loc: SourceLocation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Number<'a> {
+    pub value: Cow<'a, str>,
+    pub loc: SourceLocation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dimension<'a> {
+    pub value: Cow<'a, str>,
+    pub unit: Cow<'a, str>,
+    pub loc: SourceLocation,
+}
+
+// the value is stored without `%`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Percentage<'a> {
+    pub value: Cow<'a, str>,
+    pub loc: SourceLocation,
+}
+
+// the value is the source text, quotes included
+#[derive(Debug, Clone, PartialEq)]
+pub struct Str<'a> {
+    pub value: Cow<'a, str>,
+    pub loc: SourceLocation,
+}
+
+// the value is a String for `url("a")`, or Raw for `url(a)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Url<'a> {
+    pub value: Box<Node<'a>>,
+    pub loc: SourceLocation,
+}
+
+// `#fff`, the value is stored without `#`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hash<'a> {
+    pub value: Cow<'a, str>,
+    pub loc: SourceLocation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function<'a> {
+    pub name: Cow<'a, str>,
+    pub children: Vec<Node<'a>>,
+    pub loc: SourceLocation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parentheses<'a> {
+    pub children: Vec<Node<'a>>,
+    pub loc: SourceLocation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Brackets<'a> {
+    pub children: Vec<Node<'a>>,
+    pub loc: SourceLocation,
+}
+
+// `,`, `/`, `+` and other delimiters in a value
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operator<'a> {
+    pub value: Cow<'a, str>,
+    pub loc: SourceLocation,
+}
+
+// `U+0025-00FF`, `U+4??`
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnicodeRange<'a> {
+    pub value: Cow<'a, str>,
+    pub loc: SourceLocation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhiteSpace<'a> {
+    pub value: Cow<'a, str>,
+    pub loc: SourceLocation,
+}
+
+// source text kept as is, for custom property values and input that can't be parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Raw<'a> {
+    pub value: Cow<'a, str>,
+    pub comments: Comments<'a>,
+    pub loc: SourceLocation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CDO {
+    pub loc: SourceLocation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CDC {
+    pub loc: SourceLocation,
+}
+
+macro_rules! each_node {
+    ($node:expr, $inner:ident => $body:expr) => {
+        match $node {
+            Node::StyleSheet($inner) => $body,
+            Node::Rule($inner) => $body,
+            Node::AtRule($inner) => $body,
+            Node::AtRulePrelude($inner) => $body,
+            Node::Block($inner) => $body,
+            Node::DeclarationList($inner) => $body,
+            Node::Declaration($inner) => $body,
+            Node::Value($inner) => $body,
+            Node::SelectorList($inner) => $body,
+            Node::Selector($inner) => $body,
+            Node::TypeSelector($inner) => $body,
+            Node::IdSelector($inner) => $body,
+            Node::ClassSelector($inner) => $body,
+            Node::AttributeSelector($inner) => $body,
+            Node::PseudoClassSelector($inner) => $body,
+            Node::PseudoElementSelector($inner) => $body,
+            Node::Combinator($inner) => $body,
+            Node::Nth($inner) => $body,
+            Node::AnPlusB($inner) => $body,
+            Node::MediaQueryList($inner) => $body,
+            Node::MediaQuery($inner) => $body,
+            Node::MediaFeature($inner) => $body,
+            Node::Ratio($inner) => $body,
+            Node::Identifier($inner) => $body,
+            Node::Number($inner) => $body,
+            Node::Dimension($inner) => $body,
+            Node::Percentage($inner) => $body,
+            Node::String($inner) => $body,
+            Node::Url($inner) => $body,
+            Node::Hash($inner) => $body,
+            Node::Function($inner) => $body,
+            Node::Parentheses($inner) => $body,
+            Node::Brackets($inner) => $body,
+            Node::Operator($inner) => $body,
+            Node::UnicodeRange($inner) => $body,
+            Node::WhiteSpace($inner) => $body,
+            Node::Comment($inner) => $body,
+            Node::Raw($inner) => $body,
+            Node::CDO($inner) => $body,
+            Node::CDC($inner) => $body,
+        }
+    };
+}
+
+impl<'a> Node<'a> {
+    /// the node type name used by the JS csstree
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Node::StyleSheet(_) => "StyleSheet",
+            Node::Rule(_) => "Rule",
+            Node::AtRule(_) => "Atrule",
+            Node::AtRulePrelude(_) => "AtrulePrelude",
+            Node::Block(_) => "Block",
+            Node::DeclarationList(_) => "DeclarationList",
+            Node::Declaration(_) => "Declaration",
+            Node::Value(_) => "Value",
+            Node::SelectorList(_) => "SelectorList",
+            Node::Selector(_) => "Selector",
+            Node::TypeSelector(_) => "TypeSelector",
+            Node::IdSelector(_) => "IdSelector",
+            Node::ClassSelector(_) => "ClassSelector",
+            Node::AttributeSelector(_) => "AttributeSelector",
+            Node::PseudoClassSelector(_) => "PseudoClassSelector",
+            Node::PseudoElementSelector(_) => "PseudoElementSelector",
+            Node::Combinator(_) => "Combinator",
+            Node::Nth(_) => "Nth",
+            Node::AnPlusB(_) => "AnPlusB",
+            Node::MediaQueryList(_) => "MediaQueryList",
+            Node::MediaQuery(_) => "MediaQuery",
+            Node::MediaFeature(_) => "MediaFeature",
+            Node::Ratio(_) => "Ratio",
+            Node::Identifier(_) => "Identifier",
+            Node::Number(_) => "Number",
+            Node::Dimension(_) => "Dimension",
+            Node::Percentage(_) => "Percentage",
+            Node::String(_) => "String",
+            Node::Url(_) => "Url",
+            Node::Hash(_) => "Hash",
+            Node::Function(_) => "Function",
+            Node::Parentheses(_) => "Parentheses",
+            Node::Brackets(_) => "Brackets",
+            Node::Operator(_) => "Operator",
+            Node::UnicodeRange(_) => "UnicodeRange",
+            Node::WhiteSpace(_) => "WhiteSpace",
+            Node::Comment(_) => "Comment",
+            Node::Raw(_) => "Raw",
+            Node::CDO(_) => "CDO",
+            Node::CDC(_) => "CDC",
+        }
+    }
+    pub fn loc(&self) -> &SourceLocation {
+        each_node!(self, node => &node.loc)
+    }
+    pub fn loc_mut(&mut self) -> &mut SourceLocation {
+        each_node!(self, node => &mut node.loc)
+    }
+    /// the child list of nodes that hold one
+    pub fn children(&self) -> Option<&Vec<Node<'a>>> {
+        match self {
+            Node::StyleSheet(node) => Some(&node.children),
+            Node::AtRulePrelude(node) => Some(&node.children),
+            Node::Block(node) => Some(&node.children),
+            Node::DeclarationList(node) => Some(&node.children),
+            Node::Value(node) => Some(&node.children),
+            Node::SelectorList(node) => Some(&node.children),
+            Node::Selector(node) => Some(&node.children),
+            Node::PseudoClassSelector(node) => node.children.as_ref(),
+            Node::PseudoElementSelector(node) => node.children.as_ref(),
+            Node::MediaQueryList(node) => Some(&node.children),
+            Node::MediaQuery(node) => Some(&node.children),
+            Node::Function(node) => Some(&node.children),
+            Node::Parentheses(node) => Some(&node.children),
+            Node::Brackets(node) => Some(&node.children),
+            _ => None,
+        }
+    }
+    pub fn children_mut(&mut self) -> Option<&mut Vec<Node<'a>>> {
+        match self {
+            Node::StyleSheet(node) => Some(&mut node.children),
+            Node::AtRulePrelude(node) => Some(&mut node.children),
+            Node::Block(node) => Some(&mut node.children),
+            Node::DeclarationList(node) => Some(&mut node.children),
+            Node::Value(node) => Some(&mut node.children),
+            Node::SelectorList(node) => Some(&mut node.children),
+            Node::Selector(node) => Some(&mut node.children),
+            Node::PseudoClassSelector(node) => node.children.as_mut(),
+            Node::PseudoElementSelector(node) => node.children.as_mut(),
+            Node::MediaQueryList(node) => Some(&mut node.children),
+            Node::MediaQuery(node) => Some(&mut node.children),
+            Node::Function(node) => Some(&mut node.children),
+            Node::Parentheses(node) => Some(&mut node.children),
+            Node::Brackets(node) => Some(&mut node.children),
+            _ => None,
+        }
+    }
+    /// the comments attached to rules, at-rules, declarations, blocks and raw nodes
+    pub fn comments(&self) -> Option<&Comments<'a>> {
+        match self {
+            Node::StyleSheet(node) => Some(&node.comments),
+            Node::Rule(node) => Some(&node.comments),
+            Node::AtRule(node) => Some(&node.comments),
+            Node::Block(node) => Some(&node.comments),
+            Node::DeclarationList(node) => Some(&node.comments),
+            Node::Declaration(node) => Some(&node.comments),
+            Node::Raw(node) => Some(&node.comments),
+            _ => None,
+        }
+    }
+    pub fn comments_mut(&mut self) -> Option<&mut Comments<'a>> {
+        match self {
+            Node::StyleSheet(node) => Some(&mut node.comments),
+            Node::Rule(node) => Some(&mut node.comments),
+            Node::AtRule(node) => Some(&mut node.comments),
+            Node::Block(node) => Some(&mut node.comments),
+            Node::DeclarationList(node) => Some(&mut node.comments),
+            Node::Declaration(node) => Some(&mut node.comments),
+            Node::Raw(node) => Some(&mut node.comments),
+            _ => None,
+        }
+    }
+    /// comments before the node
+    pub fn leading_comments(&self) -> &[Comment<'a>] {
+        self.comments().map_or(&[], |c| &c.leading)
+    }
+    /// comments inside the node that don't belong to any child, e.g. in an empty block
+    pub fn inner_comments(&self) -> &[Comment<'a>] {
+        self.comments().map_or(&[], |c| &c.inner)
+    }
+    /// comments after the node, starting on the line where the node ends
+    pub fn trailing_comments(&self) -> &[Comment<'a>] {
+        self.comments().map_or(&[], |c| &c.trailing)
+    }
 }
@@ -1,57 +1,180 @@
 use super::{is_block_matched, is_block_start};
-use crate::parser::node::{Comment, Node, NodeType};
-use crate::tokenizer::source_location::{Position, SourceLocation};
+use crate::parser::component::{
+    comment_nodes, loc_of, raw, to_nodes, trim_end_whitespace, trim_whitespace,
+    trim_whitespace_slice, ComponentValue,
+};
+use crate::parser::error::{ParseError, ParseErrorCallback, ParseErrorKind};
+use crate::parser::media;
+use crate::parser::node::{
+    AtRule, AtRulePrelude, Block, Comment, Comments, Declaration, DeclarationList, Node, Rule,
+    StyleSheet, Value,
+};
+use crate::parser::selector;
+use crate::source_map::source_mapping_url;
+use crate::tokenizer::encoding::Decoded;
+use crate::tokenizer::source_location::{ColumnEncoding, Position, SourceLocation};
 use crate::tokenizer::token::Token;
 use crate::tokenizer::tokenizer::Tokenizer;
+use std::borrow::Cow;
 
 struct TokenContext<'a> {
     token: Token<'a>,
     loc: SourceLocation,
+    // comments between the previous non-whitespace token and this one
     comments: Vec<Comment<'a>>,
 }
 
+// what the `{}` block of a rule contains
+enum BlockContent {
+    Rules,
+    // the keyframe rules of `@keyframes`
+    Keyframes,
+    Declarations,
+    ComponentValues,
+}
+
 pub struct Parser<'a> {
+    input: &'a str,
     tokenizer: Tokenizer<'a>,
     options: ParserOptions,
+    // the comments of a token given back to the tokenizer
+    reconsumed_comments: Option<Vec<Comment<'a>>>,
+    pending_comments: Vec<Comment<'a>>,
+    errors: Vec<ParseError>,
+    source_mapping_url: Option<&'a str>,
 }
 
+/// the grammar entry point, like the `context` option of the JS csstree parser
 pub enum ParserContext {
     Stylesheet,
+    /// rules like in the block of an at-rule, `<!--` and `-->` start a qualified rule
+    RuleList,
+    Rule,
+    DeclarationList,
+    Declaration,
+    Value,
+    SelectorList,
+    AtRulePrelude,
+    MediaQueryList,
 }
 
 pub struct ParserOptions {
     pub context: ParserContext,
+    /// attach comments to nodes, turn it off to skip them for speed
+    pub keep_comments: bool,
+    /// called for every parse error as soon as it is found
+    pub on_parse_error: Option<ParseErrorCallback>,
+    /// what the columns of node locations count
+    pub column_encoding: ColumnEncoding,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions {
+            context: ParserContext::Stylesheet,
+            keep_comments: true,
+            on_parse_error: None,
+            column_encoding: ColumnEncoding::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseResult<'a> {
+    pub node: Node<'a>,
+    pub errors: Vec<ParseError>,
+    /// the url of the last `/*# sourceMappingURL=... */` comment, an inline map is a `data:` url
+    pub source_mapping_url: Option<&'a str>,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(input: &str, options: ParserOptions) -> Parser {
+    pub fn new(input: &'a str, options: ParserOptions) -> Parser<'a> {
+        let mut tokenizer = Tokenizer::new(input);
+        tokenizer.column_encoding = options.column_encoding;
         Parser {
-            tokenizer: Tokenizer::new(input),
+            input,
+            tokenizer,
             options,
+            reconsumed_comments: None,
+            pending_comments: vec![],
+            errors: vec![],
+            source_mapping_url: None,
         }
     }
-    fn get_token_and_loc(&mut self) -> (Token<'a>, SourceLocation) {
-        let start = Position {
-            line: self.tokenizer.line,
-            column: self.tokenizer.column,
-        };
-        let token = self.tokenizer.next_token();
-        let end = Position {
-            line: self.tokenizer.line,
-            column: self.tokenizer.column,
+    /// a parser over a stylesheet decoded from bytes by `encoding::decode`
+    pub fn from_decoded(decoded: &'a Decoded<'_>, options: ParserOptions) -> Parser<'a> {
+        Parser::new(&decoded.text, options)
+    }
+    /// parse errors found so far, the parser recovers from all of them
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+    fn error(&mut self, kind: ParseErrorKind, message: &str, loc: SourceLocation) {
+        let error = ParseError {
+            kind,
+            message: message.to_string(),
+            loc,
         };
-        let loc = SourceLocation { start, end };
-        (token, loc)
+        if let Some(callback) = self.options.on_parse_error.as_mut() {
+            callback(&error);
+        }
+        self.errors.push(error);
+    }
+    fn unexpected_eof(&mut self, loc: SourceLocation) {
+        self.error(
+            ParseErrorKind::UnexpectedEOF,
+            "Unexpected end of input",
+            loc,
+        );
+    }
+    fn unexpected_token(&mut self, loc: SourceLocation) {
+        self.error(ParseErrorKind::UnexpectedToken, "Unexpected input", loc);
+    }
+    fn empty_input(&mut self, loc: SourceLocation) {
+        self.error(ParseErrorKind::EmptyInput, "Input is empty", loc);
+    }
+    fn selector_expected(&mut self, loc: SourceLocation) {
+        self.error(
+            ParseErrorKind::SelectorExpected,
+            "Selector is expected",
+            loc,
+        );
     }
+    fn position(&self) -> Position {
+        self.tokenizer.position()
+    }
+    // comments are handed to the next non-whitespace token
     fn consume_token(&mut self) -> TokenContext<'a> {
-        let mut comments: Vec<Comment<'a>> = vec![];
+        if let Some(comments) = self.reconsumed_comments.take() {
+            let (token, loc) = self.tokenizer.next_token_with_loc();
+            return TokenContext {
+                token,
+                loc,
+                comments,
+            };
+        }
         loop {
-            let (token, loc) = self.get_token_and_loc();
-            if let Token::Comment(v) = token {
-                let comment = Comment { r#value: v, loc };
-                comments.push(comment);
-                continue;
-            }
+            let (token, loc) = self.tokenizer.next_token_with_loc();
+            let comments = match token {
+                Token::Comment(v) => {
+                    if let Some(url) = source_mapping_url(v) {
+                        self.source_mapping_url = Some(url);
+                    }
+                    if self.options.keep_comments {
+                        // the text between `/*` and `*/` like in csstree, an unclosed
+                        // comment has no `*/`
+                        let text = v.strip_prefix("/*").unwrap_or(v);
+                        let comment = Comment {
+                            r#value: Cow::Borrowed(text.strip_suffix("*/").unwrap_or(text)),
+                            loc,
+                        };
+                        self.pending_comments.push(comment);
+                    }
+                    continue;
+                }
+                Token::WhiteSpace(_) => vec![],
+                _ => std::mem::take(&mut self.pending_comments),
+            };
             return TokenContext {
                 token,
                 loc,
@@ -59,65 +182,1335 @@ impl<'a> Parser<'a> {
             };
         }
     }
-    // pub fn parse(&self) {
-    //     self.parse_stylesheet()
-    // }
-
-    // fn parse_stylesheet(&mut self) {
-    //     self.parse_list_rules(true)
-    // }
-    // fn parse_list_rules(&self, top_level: bool) -> Vec<Node<'a>> {
-    //     let nodes: Vec<Node<'a>> = Vec::with_capacity(1);
-    //     loop {
-    //         let token = self.token();
-    //         let r = match token {
-    //             Token::WhiteSpace(_) => continue,
-    //             Token::EOF => break,
-    //             Token::CDC | Token::CDO => {
-    //                 if top_level {
-    //                     continue;
-    //                 } else {
-    //                     self.parse_qualified_rule(token)
-    //                 }
-    //             }
-    //             Token::AtKeyword(_) => self.parse_at_rule(token),
-    //             _ => self.parse_qualified_rule(token),
-    //         };
-    //     }
-    //     nodes
-    // }
-    // // https://drafts.csswg.org/css-syntax-3/#qualified-rule-diagram
-    // fn parse_qualified_rule(&self) -> Node<'a> {}
-    // fn parse_at_rule(&self) {}
-    // // https://drafts.csswg.org/css-syntax-3/#consume-component-value
-    // fn consume_component_value(&self) {
-    //     let token = self.token();
-    //     if is_block_start(token) {
-    //         return self.consume_simpile_block();
-    //     }
-    //     if let Token::Function(name) = token {
-    //         return self.consume_function(name);
-    //     }
-    // }
-    // fn createNode(token: Token<'a>, r#type: NodeType) -> Node {}
-    // // https://drafts.csswg.org/css-syntax-3/#consume-a-function
-    // fn consume_function(&self, name: &'a str) -> Node<'a> {
-    //     let node = Node {
-    //         r#type: NodeType::Function(name),
-    //     };
-    // }
-    // // https://drafts.csswg.org/css-syntax-3/#consume-simple-block
-    // fn consume_simpile_block(&self, start: Token<'a>) {
-    //     let nodes: Vec<Node<'a>> = Vec::with_capacity(1);
-    //     loop {
-    //         let token = self.token();
-    //         if token == Token::EOF {
-    //             return nodes;
-    //         }
-    //         if is_block_matched(start, token) {
-    //             return nodes;
-    //         }
-    //         self.consume_component_value(token);
-    //     }
-    // }
+    // give the comments of a skipped token to the next one
+    fn defer_comments(&mut self, mut comments: Vec<Comment<'a>>) {
+        if !comments.is_empty() {
+            comments.append(&mut self.pending_comments);
+            self.pending_comments = comments;
+        }
+    }
+    // https://drafts.csswg.org/css-syntax-3/#reconsume-the-current-input-token
+    // `ctx` must be the last consumed token, its comments may have been taken
+    fn reconsume(&mut self, ctx: TokenContext<'a>) {
+        self.tokenizer.reconsume();
+        self.reconsumed_comments = Some(ctx.comments);
+    }
+    /// parse the input with the entry point of `options.context`
+    pub fn parse(&mut self) -> ParseResult<'a> {
+        let node = match self.options.context {
+            ParserContext::Stylesheet => self.parse_stylesheet(),
+            ParserContext::RuleList => self.parse_rule_list(),
+            ParserContext::Rule => self.parse_rule(),
+            ParserContext::DeclarationList => self.parse_declaration_list(),
+            ParserContext::Declaration => self.parse_declaration(),
+            ParserContext::Value => self.parse_value(),
+            ParserContext::SelectorList => self.parse_selector_list(),
+            ParserContext::AtRulePrelude => self.parse_at_rule_prelude(),
+            ParserContext::MediaQueryList => self.parse_media_query_list(),
+        };
+        ParseResult {
+            node,
+            errors: std::mem::take(&mut self.errors),
+            source_mapping_url: self.source_mapping_url,
+        }
+    }
+    // https://drafts.csswg.org/css-syntax-3/#parse-stylesheet
+    pub fn parse_stylesheet(&mut self) -> Node<'a> {
+        let mut node = Node::StyleSheet(StyleSheet {
+            children: vec![],
+            comments: Comments::default(),
+            loc: empty_loc(self.position()),
+        });
+        self.consume_list_of_rules(true, false, false, &mut node);
+        node
+    }
+    // https://drafts.csswg.org/css-syntax-3/#parse-list-of-rules
+    // the rules are children of a stylesheet node
+    pub fn parse_rule_list(&mut self) -> Node<'a> {
+        let mut node = Node::StyleSheet(StyleSheet {
+            children: vec![],
+            comments: Comments::default(),
+            loc: empty_loc(self.position()),
+        });
+        self.consume_list_of_rules(false, false, false, &mut node);
+        node
+    }
+    // https://drafts.csswg.org/css-syntax-3/#parse-rule
+    pub fn parse_rule(&mut self) -> Node<'a> {
+        self.skip_whitespace();
+        let ctx = self.consume_token();
+        let node = match ctx.token {
+            Token::EOF => {
+                self.empty_input(ctx.loc);
+                return self.raw_input();
+            }
+            Token::AtKeyword(_) => self.consume_at_rule(ctx, false),
+            _ => {
+                self.reconsume(ctx);
+                self.consume_qualified_rule(false, false)
+            }
+        };
+        if let Node::Raw(_) = node {
+            return self.raw_input();
+        }
+        self.expect_eof(node)
+    }
+    // https://drafts.csswg.org/css-syntax-3/#parse-list-of-declarations
+    pub fn parse_declaration_list(&mut self) -> Node<'a> {
+        let mut node = Node::DeclarationList(DeclarationList {
+            children: vec![],
+            comments: Comments::default(),
+            loc: empty_loc(self.position()),
+        });
+        self.consume_list_of_declarations(false, &mut node);
+        node
+    }
+    // https://drafts.csswg.org/css-syntax-3/#parse-declaration
+    pub fn parse_declaration(&mut self) -> Node<'a> {
+        self.skip_whitespace();
+        let ctx = self.consume_token();
+        if let Token::Ident(_) = ctx.token {
+            let node = self.consume_declaration(ctx, false);
+            if let Node::Raw(_) = node {
+                return self.raw_input();
+            }
+            return self.expect_eof(node);
+        }
+        if let Token::EOF = ctx.token {
+            self.empty_input(ctx.loc);
+            return self.raw_input();
+        }
+        self.error(
+            ParseErrorKind::IdentifierExpected,
+            "Identifier is expected",
+            ctx.loc,
+        );
+        self.raw_input()
+    }
+    // https://drafts.csswg.org/css-syntax-3/#parse-list-of-component-values
+    pub fn parse_value(&mut self) -> Node<'a> {
+        let start = self.position();
+        let values = trim_whitespace(self.consume_component_values());
+        let loc = loc_of(&values, empty_loc(start));
+        let mut children = to_nodes(values, self.input);
+        children.extend(comment_nodes(self.consume_token().comments));
+        Node::Value(Value { children, loc })
+    }
+    // https://drafts.csswg.org/css-syntax-3/#parse-comma-separated-list-of-component-values
+    pub fn parse_selector_list(&mut self) -> Node<'a> {
+        let start = self.position();
+        let values = trim_whitespace(self.consume_component_values());
+        let loc = loc_of(&values, empty_loc(start));
+        match selector::parse_selector_list(&values, loc, self.input) {
+            Some(node) => node,
+            None => {
+                self.selector_expected(loc);
+                self.raw_input()
+            }
+        }
+    }
+    pub fn parse_at_rule_prelude(&mut self) -> Node<'a> {
+        let start = self.position();
+        let values = trim_whitespace(self.consume_component_values());
+        let loc = loc_of(&values, empty_loc(start));
+        let mut children = to_nodes(values, self.input);
+        children.extend(comment_nodes(self.consume_token().comments));
+        Node::AtRulePrelude(AtRulePrelude { children, loc })
+    }
+    pub fn parse_media_query_list(&mut self) -> Node<'a> {
+        let start = self.position();
+        let values = trim_whitespace(self.consume_component_values());
+        let loc = loc_of(&values, empty_loc(start));
+        media::parse_media_query_list(values, loc, self.input)
+    }
+    // consume component values until the end of input
+    fn consume_component_values(&mut self) -> Vec<ComponentValue<'a>> {
+        let mut values = vec![];
+        loop {
+            let ctx = self.consume_token();
+            if let Token::EOF = ctx.token {
+                self.reconsume(ctx);
+                return values;
+            }
+            self.reconsume(ctx);
+            values.push(self.consume_component_value());
+        }
+    }
+    fn skip_whitespace(&mut self) {
+        loop {
+            let ctx = self.consume_token();
+            if let Token::WhiteSpace(_) = ctx.token {
+                continue;
+            }
+            self.reconsume(ctx);
+            return;
+        }
+    }
+    // only whitespace may follow the node of an entry point
+    fn expect_eof(&mut self, mut node: Node<'a>) -> Node<'a> {
+        self.skip_whitespace();
+        let ctx = self.consume_token();
+        if let Token::EOF = ctx.token {
+            if let Some(comments) = node.comments_mut() {
+                comments.trailing.extend(ctx.comments);
+            }
+            return node;
+        }
+        // this is a syntax error
+        self.error(
+            ParseErrorKind::ExtraInput,
+            "Unexpected input after the end",
+            ctx.loc,
+        );
+        self.raw_input()
+    }
+    // the whole input as a raw node, used when an entry point can't be parsed
+    fn raw_input(&mut self) -> Node<'a> {
+        while self.consume_token().token != Token::EOF {}
+        let loc = SourceLocation {
+            start: Position {
+                offset: 0,
+                line: 1,
+                column: 1,
+            },
+            end: self.position(),
+        };
+        raw(self.input, loc)
+    }
+    // https://drafts.csswg.org/css-syntax-3/#consume-list-of-rules
+    // `keyframes` is true for the block of `@keyframes`, where the preludes are keyframe selectors
+    fn consume_list_of_rules(
+        &mut self,
+        top_level: bool,
+        nested: bool,
+        keyframes: bool,
+        parent: &mut Node<'a>,
+    ) {
+        loop {
+            let mut ctx = self.consume_token();
+            attach_trailing_comments(parent, &mut ctx.comments);
+            match ctx.token {
+                Token::WhiteSpace(_) => continue,
+                Token::EOF => {
+                    if nested {
+                        self.unexpected_eof(ctx.loc);
+                    }
+                    close_list(parent, self.position(), ctx.comments);
+                    return;
+                }
+                Token::RightCurlyBracket if nested => {
+                    close_list(parent, ctx.loc.end, ctx.comments);
+                    return;
+                }
+                Token::CDC | Token::CDO if top_level => self.defer_comments(ctx.comments),
+                Token::AtKeyword(_) => {
+                    let node = self.consume_at_rule(ctx, nested);
+                    push_child(parent, node);
+                }
+                _ => {
+                    self.reconsume(ctx);
+                    let node = self.consume_qualified_rule(nested, keyframes);
+                    push_child(parent, node);
+                }
+            }
+        }
+    }
+    // https://drafts.csswg.org/css-syntax-3/#consume-at-rule
+    fn consume_at_rule(&mut self, ctx: TokenContext<'a>, nested: bool) -> Node<'a> {
+        let name = match ctx.token {
+            Token::AtKeyword(name) => &name[1..],
+            _ => unreachable!(),
+        };
+        let mut values = vec![];
+        let mut block = None;
+        let mut end = ctx.loc.end;
+        let mut trailing = vec![];
+        loop {
+            let ctx = self.consume_token();
+            match ctx.token {
+                Token::Semicolon => {
+                    end = ctx.loc.end;
+                    trailing = ctx.comments;
+                    break;
+                }
+                Token::EOF => {
+                    self.unexpected_eof(ctx.loc);
+                    self.reconsume(ctx);
+                    break;
+                }
+                Token::RightCurlyBracket if nested => {
+                    self.unexpected_token(ctx.loc);
+                    self.reconsume(ctx);
+                    break;
+                }
+                Token::LeftCurlyBracket => {
+                    let node = self.consume_block(ctx, at_rule_block_content(name));
+                    end = node.loc().end;
+                    block = Some(Box::new(node));
+                    break;
+                }
+                _ => {
+                    self.reconsume(ctx);
+                    let value = self.consume_component_value();
+                    end = value.loc.end;
+                    values.push(value);
+                }
+            }
+        }
+        let prelude = self.at_rule_prelude(name, values);
+        Node::AtRule(AtRule {
+            name: Cow::Borrowed(name),
+            prelude: prelude.map(Box::new),
+            block,
+            comments: Comments {
+                leading: ctx.comments,
+                trailing,
+                ..Default::default()
+            },
+            loc: SourceLocation {
+                start: ctx.loc.start,
+                end,
+            },
+        })
+    }
+    // the prelude of `@media` is a media query list, others are kept as value nodes
+    fn at_rule_prelude(&mut self, name: &str, values: Vec<ComponentValue<'a>>) -> Option<Node<'a>> {
+        let values = trim_whitespace(values);
+        let first = values.first()?;
+        let loc = loc_of(&values, first.loc);
+        let children = if strip_vendor_prefix(&name.to_ascii_lowercase()) == "media" {
+            vec![media::parse_media_query_list(values, loc, self.input)]
+        } else {
+            to_nodes(values, self.input)
+        };
+        Some(Node::AtRulePrelude(AtRulePrelude { children, loc }))
+    }
+    // https://drafts.csswg.org/css-syntax-3/#consume-qualified-rule
+    // a rule without a block is kept as a raw node
+    fn consume_qualified_rule(&mut self, nested: bool, keyframes: bool) -> Node<'a> {
+        let mut first = self.consume_token();
+        let start = first.loc.start;
+        let leading = std::mem::take(&mut first.comments);
+        let mut values = vec![];
+        self.reconsume(first);
+        loop {
+            let ctx = self.consume_token();
+            match ctx.token {
+                Token::EOF => {
+                    self.unexpected_eof(ctx.loc);
+                    self.reconsume(ctx);
+                    break;
+                }
+                Token::RightCurlyBracket if nested => {
+                    self.unexpected_token(ctx.loc);
+                    self.reconsume(ctx);
+                    break;
+                }
+                Token::LeftCurlyBracket => {
+                    let block = self.consume_block(ctx, BlockContent::Declarations);
+                    let prelude = self.rule_prelude(values, start, keyframes);
+                    return Node::Rule(Rule {
+                        loc: SourceLocation {
+                            start,
+                            end: block.loc().end,
+                        },
+                        prelude: Box::new(prelude),
+                        block: Box::new(block),
+                        comments: Comments {
+                            leading,
+                            ..Default::default()
+                        },
+                    });
+                }
+                _ => {
+                    self.reconsume(ctx);
+                    values.push(self.consume_component_value());
+                }
+            }
+        }
+        let mut node = raw(
+            self.input,
+            loc_of(trim_whitespace_slice(&values), empty_loc(start)),
+        );
+        if let Some(comments) = node.comments_mut() {
+            comments.leading = leading;
+        }
+        node
+    }
+    // the prelude of a qualified rule is a selector list, a bad one is kept as a raw node
+    fn rule_prelude(
+        &mut self,
+        values: Vec<ComponentValue<'a>>,
+        start: Position,
+        keyframes: bool,
+    ) -> Node<'a> {
+        let values = trim_whitespace(values);
+        let loc = loc_of(&values, empty_loc(start));
+        let list = if keyframes {
+            selector::parse_keyframe_selector_list(&values, loc)
+        } else {
+            selector::parse_selector_list(&values, loc, self.input)
+        };
+        match list {
+            Some(node) => node,
+            None => {
+                self.selector_expected(loc);
+                raw(self.input, loc)
+            }
+        }
+    }
+    // the `{}` block of a rule, its content depends on the rule
+    fn consume_block(&mut self, start: TokenContext<'a>, content: BlockContent) -> Node<'a> {
+        if let BlockContent::ComponentValues = content {
+            let value = self.consume_simple_block(start);
+            return Node::Block(Block {
+                loc: value.loc,
+                comments: Comments {
+                    leading: value.comments,
+                    inner: value.inner_comments,
+                    ..Default::default()
+                },
+                children: to_nodes(value.children, self.input),
+            });
+        }
+        let mut node = Node::Block(Block {
+            children: vec![],
+            comments: Comments {
+                leading: start.comments,
+                ..Default::default()
+            },
+            loc: start.loc,
+        });
+        match content {
+            BlockContent::Rules => self.consume_list_of_rules(false, true, false, &mut node),
+            BlockContent::Keyframes => self.consume_list_of_rules(false, true, true, &mut node),
+            _ => self.consume_list_of_declarations(true, &mut node),
+        }
+        node
+    }
+    // https://drafts.csswg.org/css-syntax-3/#consume-list-of-declarations
+    fn consume_list_of_declarations(&mut self, nested: bool, parent: &mut Node<'a>) {
+        loop {
+            let mut ctx = self.consume_token();
+            attach_trailing_comments(parent, &mut ctx.comments);
+            match ctx.token {
+                Token::WhiteSpace(_) => continue,
+                Token::Semicolon => self.defer_comments(ctx.comments),
+                Token::EOF => {
+                    if nested {
+                        self.unexpected_eof(ctx.loc);
+                    }
+                    close_list(parent, self.position(), ctx.comments);
+                    return;
+                }
+                Token::RightCurlyBracket if nested => {
+                    close_list(parent, ctx.loc.end, ctx.comments);
+                    return;
+                }
+                Token::AtKeyword(_) => {
+                    let node = self.consume_at_rule(ctx, nested);
+                    push_child(parent, node);
+                }
+                Token::Ident(_) => {
+                    let node = self.consume_declaration(ctx, nested);
+                    push_child(parent, node);
+                }
+                _ => {
+                    self.error(
+                        ParseErrorKind::IdentifierExpected,
+                        "Identifier is expected",
+                        ctx.loc,
+                    );
+                    let start = ctx.loc.start;
+                    let comments = std::mem::take(&mut ctx.comments);
+                    self.reconsume(ctx);
+                    let mut node = self.consume_declaration_remnants(start, nested);
+                    if let Some(c) = node.comments_mut() {
+                        c.leading = comments;
+                    }
+                    push_child(parent, node);
+                }
+            }
+        }
+    }
+    // https://drafts.csswg.org/css-syntax-3/#consume-declaration
+    // a declaration without a colon is kept as a raw node
+    fn consume_declaration(&mut self, ctx: TokenContext<'a>, nested: bool) -> Node<'a> {
+        let property = match ctx.token {
+            Token::Ident(name) => name,
+            _ => unreachable!(),
+        };
+        let start = ctx.loc.start;
+        let mut next = self.consume_token();
+        while let Token::WhiteSpace(_) = next.token {
+            next = self.consume_token();
+        }
+        if next.token != Token::Colon {
+            self.error(ParseErrorKind::ColonExpected, "Colon is expected", next.loc);
+            self.reconsume(next);
+            let mut node = self.consume_declaration_remnants(start, nested);
+            if let Some(comments) = node.comments_mut() {
+                comments.leading = ctx.comments;
+            }
+            return node;
+        }
+        let colon_end = next.loc.end;
+        let unicode_range = property.eq_ignore_ascii_case("unicode-range");
+        self.tokenizer.unicode_ranges_allowed = unicode_range;
+        let mut values: Vec<ComponentValue<'a>> = vec![];
+        let mut trailing = vec![];
+        loop {
+            let ctx = self.consume_token();
+            match ctx.token {
+                Token::Semicolon => {
+                    trailing = ctx.comments;
+                    break;
+                }
+                Token::EOF => {
+                    self.reconsume(ctx);
+                    break;
+                }
+                Token::RightCurlyBracket if nested => {
+                    self.reconsume(ctx);
+                    break;
+                }
+                _ => {
+                    self.reconsume(ctx);
+                    values.push(self.consume_component_value());
+                }
+            }
+        }
+        self.tokenizer.unicode_ranges_allowed = false;
+        trim_end_whitespace(&mut values);
+        let end = values.last().map_or(colon_end, |v| v.loc.end);
+        let important = is_important(&values);
+        if important {
+            while let Some(value) = values.pop() {
+                if value.is_delim("!") {
+                    break;
+                }
+            }
+        }
+        let values = trim_whitespace(values);
+        let loc = loc_of(&values, empty_loc(colon_end));
+        let bad_range = if unicode_range {
+            invalid_unicode_range(&values)
+        } else {
+            None
+        };
+        let value = if property.starts_with("--") {
+            // custom property values are kept as the original token sequence
+            raw(self.input, loc)
+        } else if let Some(range_loc) = bad_range {
+            self.error(
+                ParseErrorKind::InvalidUnicodeRange,
+                "Invalid unicode range",
+                range_loc,
+            );
+            raw(self.input, loc)
+        } else {
+            Node::Value(Value {
+                children: to_nodes(values, self.input),
+                loc,
+            })
+        };
+        Node::Declaration(Declaration {
+            property: Cow::Borrowed(property),
+            important,
+            value: Box::new(value),
+            comments: Comments {
+                leading: ctx.comments,
+                trailing,
+                ..Default::default()
+            },
+            loc: SourceLocation { start, end },
+        })
+    }
+    // consume component values up to the end of a bad declaration, keeping them as a raw node
+    fn consume_declaration_remnants(&mut self, start: Position, nested: bool) -> Node<'a> {
+        let mut loc = empty_loc(start);
+        loop {
+            let ctx = self.consume_token();
+            match ctx.token {
+                Token::Semicolon => break,
+                Token::EOF => {
+                    self.reconsume(ctx);
+                    break;
+                }
+                Token::RightCurlyBracket if nested => {
+                    self.reconsume(ctx);
+                    break;
+                }
+                _ => {
+                    self.reconsume(ctx);
+                    let value = self.consume_component_value();
+                    if !value.is_whitespace() {
+                        loc.end = value.loc.end;
+                    }
+                }
+            }
+        }
+        raw(self.input, loc)
+    }
+    // https://drafts.csswg.org/css-syntax-3/#consume-component-value
+    fn consume_component_value(&mut self) -> ComponentValue<'a> {
+        let ctx = self.consume_token();
+        if is_block_start(&ctx.token) {
+            return self.consume_simple_block(ctx);
+        }
+        if let Token::Function(_) = ctx.token {
+            return self.consume_function(ctx);
+        }
+        let mut value = ComponentValue::new(ctx.token, ctx.loc, ctx.comments);
+        if let Token::Url(url) = ctx.token {
+            value.children.push(self.url_value(url, ctx.loc));
+        }
+        value
+    }
+    // the text of a url token without `url(`, `)` and whitespace,
+    // it's kept as the only child of the token for its location
+    fn url_value(&self, url: &'a str, loc: SourceLocation) -> ComponentValue<'a> {
+        let encoding = self.options.column_encoding;
+        let text = &self.input[loc.range()];
+        let open = text.find('(').map_or(0, |i| i + 1);
+        let whitespace = [' ', '\t', '\n', '\r', '\x0C'];
+        let leading = text[open..].len() - text[open..].trim_start_matches(whitespace).len();
+        let start = loc.start.advance(&text[..open + leading], encoding);
+        let end = start.advance(url, encoding);
+        ComponentValue::new(Token::Url(url), SourceLocation { start, end }, vec![])
+    }
+    // https://drafts.csswg.org/css-syntax-3/#consume-simple-block
+    fn consume_simple_block(&mut self, start: TokenContext<'a>) -> ComponentValue<'a> {
+        let mut value = ComponentValue::new(start.token, start.loc, start.comments);
+        loop {
+            let ctx = self.consume_token();
+            if is_block_matched(&start.token, &ctx.token) {
+                value.loc.end = ctx.loc.end;
+                value.inner_comments = ctx.comments;
+                return value;
+            }
+            if let Token::EOF = ctx.token {
+                self.unexpected_eof(ctx.loc);
+                self.reconsume(ctx);
+                return value;
+            }
+            self.reconsume(ctx);
+            let child = self.consume_component_value();
+            value.loc.end = child.loc.end;
+            value.children.push(child);
+        }
+    }
+    // https://drafts.csswg.org/css-syntax-3/#consume-function
+    fn consume_function(&mut self, start: TokenContext<'a>) -> ComponentValue<'a> {
+        let mut value = ComponentValue::new(start.token, start.loc, start.comments);
+        loop {
+            let ctx = self.consume_token();
+            match ctx.token {
+                Token::RightParenthesis => {
+                    value.loc.end = ctx.loc.end;
+                    value.inner_comments = ctx.comments;
+                    return value;
+                }
+                Token::EOF => {
+                    self.unexpected_eof(ctx.loc);
+                    self.reconsume(ctx);
+                    return value;
+                }
+                _ => {
+                    self.reconsume(ctx);
+                    let child = self.consume_component_value();
+                    value.loc.end = child.loc.end;
+                    value.children.push(child);
+                }
+            }
+        }
+    }
+}
+
+#[inline]
+fn empty_loc(pos: Position) -> SourceLocation {
+    SourceLocation {
+        start: pos,
+        end: pos,
+    }
+}
+
+// at-rules whose block holds rules, others whose block holds declarations,
+// the block of an unknown at-rule is kept as component values
+fn at_rule_block_content(name: &str) -> BlockContent {
+    let name = name.to_ascii_lowercase();
+    let name = strip_vendor_prefix(&name);
+    match name {
+        "media" | "supports" | "document" | "layer" | "container" | "scope" | "starting-style" => {
+            BlockContent::Rules
+        }
+        "keyframes" => BlockContent::Keyframes,
+        "font-face"
+        | "page"
+        | "counter-style"
+        | "viewport"
+        | "property"
+        | "font-palette-values"
+        | "font-feature-values"
+        | "swash"
+        | "annotation"
+        | "ornaments"
+        | "stylistic"
+        | "styleset"
+        | "character-variant"
+        | "top-left-corner"
+        | "top-left"
+        | "top-center"
+        | "top-right"
+        | "top-right-corner"
+        | "bottom-left-corner"
+        | "bottom-left"
+        | "bottom-center"
+        | "bottom-right"
+        | "bottom-right-corner"
+        | "left-top"
+        | "left-middle"
+        | "left-bottom"
+        | "right-top"
+        | "right-middle"
+        | "right-bottom" => BlockContent::Declarations,
+        _ => BlockContent::ComponentValues,
+    }
+}
+
+// -webkit-keyframes => keyframes
+fn strip_vendor_prefix(name: &str) -> &str {
+    if let Some(rest) = name.strip_prefix('-') {
+        if let Some(index) = rest.find('-') {
+            return &rest[index + 1..];
+        }
+    }
+    name
+}
+
+// comments starting on the line where the previous node ends belong to that node
+fn attach_trailing_comments<'a>(parent: &mut Node<'a>, comments: &mut Vec<Comment<'a>>) {
+    let last = parent
+        .children_mut()
+        .and_then(|children| children.last_mut());
+    if let Some(last) = last {
+        let line = last.loc().end.line;
+        let count = comments
+            .iter()
+            .take_while(|c| c.loc.start.line == line)
+            .count();
+        if let Some(last) = last.comments_mut() {
+            last.trailing.extend(comments.drain(..count));
+        }
+    }
+}
+
+// the end of a rule or declaration list, the comments left belong to the list itself
+fn close_list<'a>(parent: &mut Node<'a>, end: Position, comments: Vec<Comment<'a>>) {
+    parent.loc_mut().end = end;
+    if let Some(parent) = parent.comments_mut() {
+        parent.inner = comments;
+    }
+}
+
+// the last two non-whitespace values are `!` and `important`
+fn is_important(values: &[ComponentValue]) -> bool {
+    let mut iter = values.iter().rev().filter(|v| !v.is_whitespace());
+    let last = iter.next();
+    let prev = iter.next();
+    match (prev, last) {
+        (Some(prev), Some(last)) => prev.is_delim("!") && last.is_ident("important"),
+        _ => false,
+    }
+}
+
+// https://drafts.csswg.org/css-syntax-3/#urange-syntax
+// a range past U+10FFFF, an empty one, or one followed by more digits or `?` like `U+1234567`
+fn invalid_unicode_range(values: &[ComponentValue]) -> Option<SourceLocation> {
+    values
+        .iter()
+        .enumerate()
+        .find_map(|(i, value)| match value.token {
+            Token::UnicodeRange(range) => {
+                let joined = values
+                    .get(i + 1)
+                    .is_some_and(|next| !next.is_whitespace() && next.token != Token::Comma);
+                (!range.is_valid() || joined).then_some(value.loc)
+            }
+            _ => None,
+        })
+}
+
+#[inline]
+fn push_child<'a>(parent: &mut Node<'a>, child: Node<'a>) {
+    if let Some(children) = parent.children_mut() {
+        children.push(child);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::node::Str;
+
+    fn parse(input: &str) -> Node<'_> {
+        parse_with(ParserContext::Stylesheet, input)
+    }
+
+    fn parse_with(context: ParserContext, input: &str) -> Node<'_> {
+        let options = ParserOptions {
+            context,
+            ..Default::default()
+        };
+        Parser::new(input, options).parse().node
+    }
+
+    fn children<'a, 'b>(node: &'b Node<'a>) -> &'b Vec<Node<'a>> {
+        node.children().expect("expected a node with children")
+    }
+
+    fn type_names(nodes: &[Node]) -> Vec<&'static str> {
+        nodes.iter().map(|n| n.type_name()).collect()
+    }
+
+    fn raw_value<'b>(node: &'b Node) -> &'b str {
+        match node {
+            Node::Raw(raw) => &raw.value,
+            _ => panic!("expected raw, got {}", node.type_name()),
+        }
+    }
+
+    #[test]
+    fn parse_rules() {
+        let node = parse("@import 'a.css'; a { color: red } @media print { b {} }");
+        assert_eq!(children(&node).len(), 3);
+        match &children(&node)[0] {
+            Node::AtRule(at_rule) => {
+                assert_eq!(at_rule.name, "import");
+                assert!(at_rule.block.is_none());
+            }
+            _ => panic!("expected at-rule"),
+        }
+        let rule = rule_at(&node, 1);
+        assert_eq!(children(&rule.prelude).len(), 1);
+        assert_eq!(rule.block.loc().start.column, 20);
+        assert_eq!(rule.block.loc().end.column, 34);
+    }
+
+    fn declarations<'a, 'b>(node: &'b Node<'a>) -> Vec<&'b Declaration<'a>> {
+        children(node)
+            .iter()
+            .filter_map(|n| match n {
+                Node::Declaration(declaration) => Some(declaration),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn rule_at<'a, 'b>(node: &'b Node<'a>, index: usize) -> &'b Rule<'a> {
+        match &children(node)[index] {
+            Node::Rule(rule) => rule,
+            _ => panic!("expected rule"),
+        }
+    }
+
+    fn first_rule<'a, 'b>(node: &'b Node<'a>) -> &'b Rule<'a> {
+        rule_at(node, 0)
+    }
+
+    #[test]
+    fn parse_function_and_blocks() {
+        let node = parse("a { b: rgb(1, (2)) url( 'x.png' ) url(y.png ) }");
+        let rule = first_rule(&node);
+        let declaration = declarations(&rule.block)[0];
+        let value = children(&declaration.value);
+        assert_eq!(
+            type_names(value),
+            vec!["Function", "WhiteSpace", "Url", "WhiteSpace", "Url"]
+        );
+        match &value[0] {
+            Node::Function(function) => {
+                assert_eq!(function.name, "rgb");
+                assert_eq!(function.children.last().unwrap().type_name(), "Parentheses");
+            }
+            _ => panic!("expected function"),
+        }
+        match &value[2] {
+            Node::Url(url) => assert!(
+                matches!(&*url.value, Node::String(Str { value, .. }) if value == "'x.png'")
+            ),
+            _ => panic!("expected url"),
+        }
+        match &value[4] {
+            Node::Url(url) => {
+                assert_eq!(raw_value(&url.value), "y.png");
+                assert_eq!(url.value.loc().start.column, 39);
+                assert_eq!(url.value.loc().end.column, 44);
+            }
+            _ => panic!("expected url"),
+        }
+        let node = parse_with(ParserContext::Value, "url(\n  ü.png\n)");
+        match &children(&node)[0] {
+            Node::Url(url) => {
+                let loc = url.value.loc();
+                assert_eq!((loc.start.line, loc.start.column), (2, 3));
+                assert_eq!((loc.end.line, loc.end.column), (2, 8));
+                assert_eq!(&"url(\n  ü.png\n)"[loc.range()], "ü.png");
+            }
+            _ => panic!("expected url"),
+        }
+        let node = parse_with(ParserContext::Value, "url( a\\  )");
+        match &children(&node)[0] {
+            Node::Url(url) => {
+                assert_eq!(raw_value(&url.value), "a\\ ");
+                assert_eq!(url.value.loc().range(), 5..8);
+            }
+            _ => panic!("expected url"),
+        }
+    }
+
+    #[test]
+    fn parse_declarations() {
+        let node = parse("a { color : red ! IMPORTANT ; margin: 0 auto; --x: { a } ; top:}");
+        let rule = first_rule(&node);
+        let list = declarations(&rule.block);
+        assert_eq!(list.len(), 4);
+        assert_eq!(list[0].property, "color");
+        assert!(list[0].important);
+        assert_eq!(children(&list[0].value).len(), 1);
+        assert_eq!(children(&rule.block)[0].loc().end.column, 28);
+        assert!(!list[1].important);
+        assert_eq!(
+            type_names(children(&list[1].value)),
+            vec!["Number", "WhiteSpace", "Identifier"]
+        );
+        assert_eq!(raw_value(&list[2].value), "{ a }");
+        assert!(children(&list[3].value).is_empty());
+    }
+
+    #[test]
+    fn parse_value_nodes() {
+        let node = parse_with(ParserContext::Value, "1.5em -2% #fff 'a' 10 / 2, +.5e3");
+        let names: Vec<&str> = type_names(children(&node))
+            .into_iter()
+            .filter(|n| *n != "WhiteSpace")
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "Dimension",
+                "Percentage",
+                "Hash",
+                "String",
+                "Number",
+                "Operator",
+                "Number",
+                "Operator",
+                "Number"
+            ]
+        );
+        match &children(&node)[0] {
+            Node::Dimension(dimension) => {
+                assert_eq!(dimension.value, "1.5");
+                assert_eq!(dimension.unit, "em");
+            }
+            _ => panic!("expected dimension"),
+        }
+    }
+
+    #[test]
+    fn parse_unicode_ranges() {
+        let node = parse_with(
+            ParserContext::DeclarationList,
+            "unicode-range: U+0025-00FF, u+4??; margin: U+4",
+        );
+        let declarations = declarations(&node);
+        let values: Vec<(&str, &str)> = children(&declarations[0].value)
+            .iter()
+            .chain(children(&declarations[1].value))
+            .filter_map(|n| match n {
+                Node::UnicodeRange(range) => Some((n.type_name(), range.value.as_ref())),
+                Node::Operator(operator) => Some((n.type_name(), operator.value.as_ref())),
+                Node::Identifier(ident) => Some((n.type_name(), ident.name.as_ref())),
+                Node::Number(number) => Some((n.type_name(), number.value.as_ref())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("UnicodeRange", "U+0025-00FF"),
+                ("Operator", ","),
+                ("UnicodeRange", "u+4??"),
+                ("Identifier", "U"),
+                ("Number", "+4")
+            ]
+        );
+
+        for input in &[
+            "unicode-range: U+1234567",
+            "unicode-range: U+110000",
+            "unicode-range: U+20-10",
+            "unicode-range: U+4??5",
+        ] {
+            let options = ParserOptions {
+                context: ParserContext::Declaration,
+                ..Default::default()
+            };
+            let result = Parser::new(input, options).parse();
+            let errors: Vec<ParseErrorKind> = result.errors.iter().map(|e| e.kind).collect();
+            assert_eq!(
+                errors,
+                vec![ParseErrorKind::InvalidUnicodeRange],
+                "{}",
+                input
+            );
+            match &result.node {
+                Node::Declaration(declaration) => assert_eq!(
+                    raw_value(&declaration.value),
+                    &input["unicode-range: ".len()..]
+                ),
+                _ => panic!("expected declaration"),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_selectors() {
+        let node = parse_with(
+            ParserContext::SelectorList,
+            "ns|a > .b + #c ~ [d|='e' i]::before, li:nth-child(2n + 1 of .x):not(.y, p)",
+        );
+        let list = children(&node);
+        assert_eq!(
+            type_names(children(&list[0])),
+            vec![
+                "TypeSelector",
+                "Combinator",
+                "ClassSelector",
+                "Combinator",
+                "IdSelector",
+                "Combinator",
+                "AttributeSelector",
+                "PseudoElementSelector"
+            ]
+        );
+        match &children(&list[0])[6] {
+            Node::AttributeSelector(attribute) => {
+                assert_eq!(attribute.matcher.as_deref(), Some("|="));
+                assert_eq!(attribute.flags.as_deref(), Some("i"));
+            }
+            _ => panic!("expected attribute selector"),
+        }
+        let nth = match &children(&list[1])[1] {
+            Node::PseudoClassSelector(pseudo) => &pseudo.children.as_ref().unwrap()[0],
+            _ => panic!("expected pseudo class"),
+        };
+        match nth {
+            Node::Nth(nth) => {
+                assert!(
+                    matches!(&*nth.nth, Node::AnPlusB(n) if n.a.as_deref() == Some("2") && n.b.as_deref() == Some("1"))
+                );
+                assert!(nth.selector.is_some());
+            }
+            _ => panic!("expected nth"),
+        }
+        match &children(&list[1])[2] {
+            Node::PseudoClassSelector(pseudo) => {
+                assert_eq!(
+                    pseudo.children.as_ref().unwrap()[0].type_name(),
+                    "SelectorList"
+                );
+            }
+            _ => panic!("expected pseudo class"),
+        }
+    }
+
+    #[test]
+    fn parse_an_plus_b() {
+        let cases = [
+            ("odd", None),
+            ("5", Some((None, Some("5")))),
+            ("-n+3", Some((Some("-1"), Some("3")))),
+            ("n- 2", Some((Some("1"), Some("-2")))),
+            ("+n", Some((Some("1"), None))),
+            ("3n-1", Some((Some("3"), Some("-1")))),
+        ];
+        for (input, expected) in cases {
+            let selector = format!(":nth-child({})", input);
+            let node = parse_with(ParserContext::SelectorList, &selector);
+            let pseudo = &children(&children(&node)[0])[0];
+            let nth = match &children(pseudo)[0] {
+                Node::Nth(nth) => &nth.nth,
+                other => panic!("expected nth for {}, got {}", input, other.type_name()),
+            };
+            match (&**nth, expected) {
+                (Node::Identifier(_), None) => {}
+                (Node::AnPlusB(n), Some((a, b))) => {
+                    assert_eq!((n.a.as_deref(), n.b.as_deref()), (a, b), "{}", input);
+                }
+                _ => panic!("unexpected nth for {}", input),
+            }
+        }
+        let node = parse_with(ParserContext::SelectorList, ":nth-child(+ n)");
+        let pseudo = &children(&children(&node)[0])[0];
+        assert_eq!(children(pseudo)[0].type_name(), "Raw");
+    }
+
+    #[test]
+    fn parse_media_queries() {
+        let node = parse("@media screen and (min-width: 100px), (aspect-ratio: 16/9) {}");
+        let prelude = match &children(&node)[0] {
+            Node::AtRule(at_rule) => at_rule.prelude.as_ref().unwrap(),
+            _ => panic!("expected at-rule"),
+        };
+        let list = &children(prelude)[0];
+        assert_eq!(list.type_name(), "MediaQueryList");
+        let queries = children(list);
+        assert_eq!(
+            type_names(children(&queries[0])),
+            vec!["Identifier", "Identifier", "MediaFeature"]
+        );
+        match &children(&queries[1])[0] {
+            Node::MediaFeature(feature) => {
+                assert_eq!(feature.name, "aspect-ratio");
+                assert_eq!(feature.value.as_ref().unwrap().type_name(), "Ratio");
+            }
+            _ => panic!("expected media feature"),
+        }
+    }
+
+    #[test]
+    fn parse_at_rule_blocks() {
+        let node =
+            parse("@media print { a { color: red } } @font-face { src: url(a.woff) } @x { 1 }");
+        let block = |index: usize| match &children(&node)[index] {
+            Node::AtRule(at_rule) => at_rule.block.as_ref().unwrap(),
+            _ => panic!("expected at-rule"),
+        };
+        assert_eq!(children(block(0))[0].type_name(), "Rule");
+        assert_eq!(declarations(block(1)).len(), 1);
+        assert_eq!(
+            type_names(children(block(2))),
+            vec!["WhiteSpace", "Number", "WhiteSpace"]
+        );
+    }
+
+    #[test]
+    fn parse_keyframe_selectors() {
+        let node = parse("@keyframes x { from, 50% { top: 0 } TO {} }");
+        let block = match &children(&node)[0] {
+            Node::AtRule(at_rule) => at_rule.block.as_ref().unwrap(),
+            _ => panic!("expected at-rule"),
+        };
+        let prelude = &rule_at(block, 0).prelude;
+        assert_eq!(
+            children(prelude)
+                .iter()
+                .map(|selector| type_names(children(selector)))
+                .collect::<Vec<_>>(),
+            vec![vec!["TypeSelector"], vec!["Percentage"]]
+        );
+        assert_eq!(
+            type_names(children(&rule_at(block, 1).prelude)),
+            vec!["Selector"]
+        );
+
+        // a percentage is only a selector in `@keyframes`
+        let result = Parser::new("div 50% {} @keyframes x { a {} }", Default::default()).parse();
+        assert_eq!(raw_value(&first_rule(&result.node).prelude), "div 50%");
+        let kinds: Vec<ParseErrorKind> = result.errors.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ParseErrorKind::SelectorExpected,
+                ParseErrorKind::SelectorExpected
+            ]
+        );
+    }
+
+    #[test]
+    fn keep_unclosed_qualified_rule_as_raw() {
+        let result = Parser::new("a { } b", Default::default()).parse();
+        assert_eq!(children(&result.node).len(), 2);
+        assert_eq!(raw_value(&children(&result.node)[1]), "b");
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].kind, ParseErrorKind::UnexpectedEOF);
+    }
+
+    #[test]
+    fn keep_bad_selector_as_raw() {
+        let result = Parser::new("a, { color: red }", Default::default()).parse();
+        let rule = first_rule(&result.node);
+        assert_eq!(raw_value(&rule.prelude), "a,");
+        assert_eq!(declarations(&rule.block).len(), 1);
+        assert_eq!(result.errors[0].kind, ParseErrorKind::SelectorExpected);
+
+        // a hash that isn't an identifier is no id selector
+        let result = Parser::new("#1a { color: red }", Default::default()).parse();
+        assert_eq!(raw_value(&first_rule(&result.node).prelude), "#1a");
+        assert_eq!(result.errors[0].kind, ParseErrorKind::SelectorExpected);
+    }
+
+    #[test]
+    fn recover_from_bad_declarations() {
+        let input = "a { color red; *zoom: 1 } b { width: 1px";
+        let result = Parser::new(input, Default::default()).parse();
+        let rule = first_rule(&result.node);
+        assert_eq!(raw_value(&children(&rule.block)[0]), "color red");
+        assert_eq!(raw_value(&children(&rule.block)[1]), "*zoom: 1");
+        let kinds: Vec<ParseErrorKind> = result.errors.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ParseErrorKind::ColonExpected,
+                ParseErrorKind::IdentifierExpected,
+                ParseErrorKind::UnexpectedEOF
+            ]
+        );
+        assert_eq!(result.errors[0].loc.start.column, 11);
+    }
+
+    #[test]
+    fn report_errors_to_callback() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+        let messages = Rc::new(RefCell::new(vec![]));
+        let collected = Rc::clone(&messages);
+        let options = ParserOptions {
+            on_parse_error: Some(Box::new(move |error: &ParseError| {
+                collected.borrow_mut().push(error.to_string());
+            })),
+            ..Default::default()
+        };
+        Parser::new("a { b: (1", options).parse();
+        assert_eq!(
+            *messages.borrow(),
+            vec!["Unexpected end of input (1:10)".to_string(); 2]
+        );
+    }
+
+    #[test]
+    fn parse_contexts() {
+        let node = parse_with(ParserContext::DeclarationList, "color: red; margin: 0");
+        assert_eq!(node.type_name(), "DeclarationList");
+        assert_eq!(declarations(&node).len(), 2);
+
+        let node = parse_with(ParserContext::Declaration, " color: red ");
+        assert_eq!(node.type_name(), "Declaration");
+        let node = parse_with(ParserContext::Declaration, "color: red; margin: 0");
+        assert_eq!(node.type_name(), "Raw");
+
+        let node = parse_with(ParserContext::Value, " 1px solid red ");
+        assert_eq!(node.type_name(), "Value");
+        assert_eq!(children(&node).len(), 5);
+
+        let node = parse_with(ParserContext::SelectorList, "a b , .c");
+        assert_eq!(node.type_name(), "SelectorList");
+        assert_eq!(children(&node).len(), 2);
+        assert_eq!(children(&children(&node)[0]).len(), 3);
+        assert_eq!(children(&node)[1].loc().start.column, 7);
+
+        let node = parse_with(ParserContext::MediaQueryList, "screen, print and (color)");
+        assert_eq!(node.type_name(), "MediaQueryList");
+        assert_eq!(children(&node)[1].type_name(), "MediaQuery");
+
+        let node = parse_with(ParserContext::AtRulePrelude, "screen and (color)");
+        assert_eq!(node.type_name(), "AtrulePrelude");
+
+        let node = parse_with(ParserContext::Rule, " a { color: red } ");
+        assert_eq!(node.type_name(), "Rule");
+        let node = parse_with(ParserContext::Rule, "a {} b {}");
+        assert_eq!(raw_value(&node), "a {} b {}");
+    }
+
+    fn comment_values<'a, 'b>(comments: &'b [Comment<'a>]) -> Vec<&'b str> {
+        comments.iter().map(|c| &*c.value).collect()
+    }
+
+    #[test]
+    fn attach_comments() {
+        let input = "/* a */\na { /* b */ color: red /* c */; /* d */\n/* e */ top: 0 }\nb { /* f */ }\n/* g */";
+        let node = parse(input);
+        let rule = &children(&node)[0];
+        assert_eq!(comment_values(rule.leading_comments()), vec![" a "]);
+        let block = &first_rule(&node).block;
+        let color = &children(block)[0];
+        assert_eq!(comment_values(color.leading_comments()), vec![" b "]);
+        assert_eq!(
+            comment_values(color.trailing_comments()),
+            vec![" c ", " d "]
+        );
+        assert_eq!(
+            comment_values(children(block)[1].leading_comments()),
+            vec![" e "]
+        );
+        let empty = &rule_at(&node, 1).block;
+        assert_eq!(comment_values(empty.inner_comments()), vec![" f "]);
+        assert_eq!(comment_values(node.inner_comments()), vec![" g "]);
+    }
+
+    #[test]
+    fn keep_comments_in_preludes() {
+        let node = parse_with(
+            ParserContext::SelectorList,
+            "a/* 1 */ /* 2 */b /* 3 */> [c /* 4 */] /* 5 */, :not(d /* 6 */)",
+        );
+        let list = children(&node);
+        assert_eq!(
+            type_names(children(&list[0])),
+            vec![
+                "TypeSelector",
+                "Comment",
+                "Combinator",
+                "Comment",
+                "TypeSelector",
+                "Comment",
+                "Combinator",
+                "Comment",
+                "AttributeSelector",
+                "Comment"
+            ]
+        );
+        let not = &children(&list[1])[0];
+        assert_eq!(type_names(children(not)), vec!["SelectorList", "Comment"]);
+
+        let node = parse_with(
+            ParserContext::MediaQueryList,
+            "screen /* 1 */ and (color /* 2 */) /* 3 */, print",
+        );
+        assert_eq!(
+            type_names(children(&children(&node)[0])),
+            vec![
+                "Identifier",
+                "Comment",
+                "Identifier",
+                "Comment",
+                "MediaFeature",
+                "Comment"
+            ]
+        );
+    }
+
+    #[test]
+    fn drop_comments() {
+        let options = ParserOptions {
+            keep_comments: false,
+            ..Default::default()
+        };
+        let node = Parser::new("/* a */ a { /* b */ }", options).parse().node;
+        assert!(children(&node)[0].leading_comments().is_empty());
+    }
+
+    #[test]
+    fn parse_decoded_bytes() {
+        use crate::tokenizer::encoding::decode;
+
+        let decoded = decode(b"@charset \"latin1\"; a { content: \"\xE9\" }", None);
+        let node = Parser::from_decoded(&decoded, ParserOptions::default())
+            .parse()
+            .node;
+        assert_eq!(type_names(children(&node)), vec!["Atrule", "Rule"]);
+        assert!(decoded.text.ends_with("\"\u{E9}\" }"));
+    }
 }
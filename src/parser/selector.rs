@@ -0,0 +1,486 @@
+use crate::parser::component::{
+    comment_nodes, loc_of, nested_comments, raw, split_commas, trim_whitespace_slice,
+    ComponentValue,
+};
+use crate::parser::node::{
+    AnPlusB, AttributeSelector, ClassSelector, Combinator, IdSelector, Identifier, Node, Nth,
+    Percentage, PseudoClassSelector, PseudoElementSelector, Selector, SelectorList, Str,
+    TypeSelector,
+};
+use crate::tokenizer::source_location::SourceLocation;
+use crate::tokenizer::token::{HashType, Numeric, Token};
+use std::borrow::Cow;
+
+// https://drafts.csswg.org/selectors-4/#grammar
+// returns `None` when the component values aren't a valid selector list
+pub(crate) fn parse_selector_list<'a>(
+    values: &[ComponentValue<'a>],
+    loc: SourceLocation,
+    input: &'a str,
+) -> Option<Node<'a>> {
+    let mut children = vec![];
+    for (part, comments) in split_commas(values) {
+        let mut selector = parse_selector(trim_whitespace_slice(part), input)?;
+        if let Some(children) = selector.children_mut() {
+            children.extend(comment_nodes(comments.to_vec()));
+        }
+        children.push(selector);
+    }
+    Some(Node::SelectorList(SelectorList { children, loc }))
+}
+
+// https://drafts.csswg.org/css-animations-1/#typedef-keyframe-selector
+// `from`, `to` and percentages in the block of `@keyframes`
+pub(crate) fn parse_keyframe_selector_list<'a>(
+    values: &[ComponentValue<'a>],
+    loc: SourceLocation,
+) -> Option<Node<'a>> {
+    let mut children = vec![];
+    for (part, comments) in split_commas(values) {
+        let (value, node) = match trim_whitespace_slice(part) {
+            [value] => (
+                value,
+                match value.token {
+                    Token::Percentage(numeric) => Node::Percentage(Percentage {
+                        value: Cow::Borrowed(numeric.number),
+                        loc: value.loc,
+                    }),
+                    Token::Ident(name) if value.is_ident("from") || value.is_ident("to") => {
+                        Node::TypeSelector(TypeSelector {
+                            name: Cow::Borrowed(name),
+                            loc: value.loc,
+                        })
+                    }
+                    _ => return None,
+                },
+            ),
+            _ => return None,
+        };
+        let loc = *node.loc();
+        let mut nodes: Vec<Node<'a>> = comment_nodes(value.comments.clone()).collect();
+        nodes.push(node);
+        nodes.extend(comment_nodes(comments.to_vec()));
+        children.push(Node::Selector(Selector {
+            children: nodes,
+            loc,
+        }));
+    }
+    Some(Node::SelectorList(SelectorList { children, loc }))
+}
+
+fn parse_selector<'a>(values: &[ComponentValue<'a>], input: &'a str) -> Option<Node<'a>> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut children: Vec<Node<'a>> = vec![];
+    let mut descendant: Option<SourceLocation> = None;
+    let mut i = 0;
+    while i < values.len() {
+        let value = &values[i];
+        if value.is_whitespace() {
+            let last = last_node(&children);
+            if last.is_some() && !is_combinator(last) {
+                descendant = Some(value.loc);
+            }
+            i += 1;
+            continue;
+        }
+        // comments are kept in the order of the source, around a descendant combinator too
+        let (before, after): (Vec<_>, Vec<_>) = value
+            .comments
+            .iter()
+            .cloned()
+            .partition(|c| descendant.is_none_or(|loc| c.loc.start.offset < loc.start.offset));
+        children.extend(comment_nodes(before));
+        if let Token::Delim(name @ (">" | "+" | "~")) = value.token {
+            // a leading combinator is allowed for relative selectors like `:has(> a)`
+            if is_combinator(last_node(&children)) {
+                return None;
+            }
+            children.extend(comment_nodes(after));
+            descendant = None;
+            children.push(Node::Combinator(Combinator {
+                name: Cow::Borrowed(name),
+                loc: value.loc,
+            }));
+            i += 1;
+            continue;
+        }
+        if let Some(loc) = descendant.take() {
+            children.push(Node::Combinator(Combinator {
+                name: Cow::Borrowed(" "),
+                loc,
+            }));
+        }
+        children.extend(comment_nodes(after));
+        let (node, consumed) = parse_simple_selector(&values[i..], input)?;
+        // the comments between the parts of a simple selector like `.a` or `::b`
+        for value in &values[i + 1..i + consumed] {
+            children.extend(comment_nodes(value.comments.clone()));
+        }
+        if let Node::AttributeSelector(_) = node {
+            children.extend(comment_nodes(nested_comments(value)));
+        }
+        children.push(node);
+        i += consumed;
+    }
+    if is_combinator(last_node(&children)) {
+        return None;
+    }
+    let loc = loc_of(values, values[0].loc);
+    Some(Node::Selector(Selector { children, loc }))
+}
+
+// the last child that isn't a comment
+#[inline]
+fn last_node<'b, 'a>(children: &'b [Node<'a>]) -> Option<&'b Node<'a>> {
+    children
+        .iter()
+        .rev()
+        .find(|node| !matches!(node, Node::Comment(_)))
+}
+
+#[inline]
+fn is_combinator(node: Option<&Node>) -> bool {
+    matches!(node, Some(Node::Combinator(_)))
+}
+
+// returns the node and how many component values it took
+fn parse_simple_selector<'a>(
+    values: &[ComponentValue<'a>],
+    input: &'a str,
+) -> Option<(Node<'a>, usize)> {
+    let first = &values[0];
+    match first.token {
+        Token::Ident(_) | Token::Delim("*") | Token::Delim("|") => {
+            parse_type_selector(values, input)
+        }
+        // `#123` isn't an id selector
+        Token::Hash(name, HashType::Id) => {
+            let node = Node::IdSelector(IdSelector {
+                name: Cow::Borrowed(name),
+                loc: first.loc,
+            });
+            Some((node, 1))
+        }
+        Token::Delim(".") => match values.get(1).map(|v| v.token) {
+            Some(Token::Ident(name)) => {
+                let node = Node::ClassSelector(ClassSelector {
+                    name: Cow::Borrowed(name),
+                    loc: span(first.loc, values[1].loc),
+                });
+                Some((node, 2))
+            }
+            _ => None,
+        },
+        Token::Colon => parse_pseudo_selector(values, input),
+        Token::LeftSquareBracket => Some((parse_attribute_selector(first, input)?, 1)),
+        _ => None,
+    }
+}
+
+#[inline]
+fn span(start: SourceLocation, end: SourceLocation) -> SourceLocation {
+    SourceLocation {
+        start: start.start,
+        end: end.end,
+    }
+}
+
+#[inline]
+fn is_name_or_any(value: Option<&ComponentValue>) -> bool {
+    value.is_some_and(|v| matches!(v.token, Token::Ident(_) | Token::Delim("*")))
+}
+
+// `a`, `*`, `ns|a`, `*|*`, `|a`
+// returns the number of component values for a name with an optional namespace prefix
+fn qualified_name_length(values: &[ComponentValue]) -> Option<usize> {
+    let first = values.first();
+    if first.is_some_and(|v| v.is_delim("|")) {
+        return if is_name_or_any(values.get(1)) {
+            Some(2)
+        } else {
+            None
+        };
+    }
+    if !is_name_or_any(first) {
+        return None;
+    }
+    if values.get(1).is_some_and(|v| v.is_delim("|")) && is_name_or_any(values.get(2)) {
+        return Some(3);
+    }
+    Some(1)
+}
+
+fn text_of<'a>(values: &[ComponentValue], input: &'a str) -> &'a str {
+    let loc = loc_of(values, values[0].loc);
+    &input[loc.start.offset..loc.end.offset]
+}
+
+fn parse_type_selector<'a>(
+    values: &[ComponentValue<'a>],
+    input: &'a str,
+) -> Option<(Node<'a>, usize)> {
+    let length = qualified_name_length(values)?;
+    let node = Node::TypeSelector(TypeSelector {
+        name: Cow::Borrowed(text_of(&values[..length], input)),
+        loc: loc_of(&values[..length], values[0].loc),
+    });
+    Some((node, length))
+}
+
+// https://drafts.csswg.org/selectors-4/#attribute-selectors
+fn parse_attribute_selector<'a>(block: &ComponentValue<'a>, input: &'a str) -> Option<Node<'a>> {
+    let owned: Vec<ComponentValue<'a>> = block
+        .children
+        .iter()
+        .filter(|v| !v.is_whitespace())
+        .map(|v| ComponentValue::new(v.token, v.loc, vec![]))
+        .collect();
+    // `|` of a namespace prefix and the start of a `|=` matcher look the same,
+    // a prefix is only taken when a name follows it
+    let length = qualified_name_length(&owned)?;
+    if owned[length - 1].is_delim("*") {
+        return None;
+    }
+    let name = Node::Identifier(Identifier {
+        name: Cow::Borrowed(text_of(&owned[..length], input)),
+        loc: loc_of(&owned[..length], owned[0].loc),
+    });
+    let rest = &owned[length..];
+    let mut attribute = AttributeSelector {
+        name: Box::new(name),
+        matcher: None,
+        value: None,
+        flags: None,
+        loc: block.loc,
+    };
+    if rest.is_empty() {
+        return Some(Node::AttributeSelector(attribute));
+    }
+    let (matcher, rest) = match (&rest[0].token, rest.get(1).map(|v| v.token)) {
+        (Token::Delim("="), _) => ("=", &rest[1..]),
+        (Token::Delim("~" | "|" | "^" | "$" | "*"), Some(Token::Delim("="))) => {
+            if rest[0].loc.end.offset != rest[1].loc.start.offset {
+                return None;
+            }
+            (
+                &input[rest[0].loc.start.offset..rest[1].loc.end.offset],
+                &rest[2..],
+            )
+        }
+        _ => return None,
+    };
+    attribute.matcher = Some(Cow::Borrowed(matcher));
+    let value = rest.first()?;
+    attribute.value = Some(Box::new(match value.token {
+        Token::Ident(name) => Node::Identifier(Identifier {
+            name: Cow::Borrowed(name),
+            loc: value.loc,
+        }),
+        Token::String(s) => Node::String(Str {
+            value: Cow::Borrowed(s),
+            loc: value.loc,
+        }),
+        _ => return None,
+    }));
+    match &rest[1..] {
+        [] => {}
+        [flags] => match flags.token {
+            Token::Ident(name) => attribute.flags = Some(Cow::Borrowed(name)),
+            _ => return None,
+        },
+        _ => return None,
+    }
+    Some(Node::AttributeSelector(attribute))
+}
+
+// https://drafts.csswg.org/selectors-4/#pseudo-classes
+fn parse_pseudo_selector<'a>(
+    values: &[ComponentValue<'a>],
+    input: &'a str,
+) -> Option<(Node<'a>, usize)> {
+    let is_element = values.get(1).is_some_and(|v| v.token == Token::Colon);
+    let index = if is_element { 2 } else { 1 };
+    let value = values.get(index)?;
+    let loc = span(values[0].loc, value.loc);
+    let (name, children) = match value.token {
+        Token::Ident(name) => (name, None),
+        Token::Function(name) => (name, Some(parse_pseudo_arguments(name, value, input))),
+        _ => return None,
+    };
+    let name = Cow::Borrowed(name);
+    let node = if is_element {
+        Node::PseudoElementSelector(PseudoElementSelector {
+            name,
+            children,
+            loc,
+        })
+    } else {
+        Node::PseudoClassSelector(PseudoClassSelector {
+            name,
+            children,
+            loc,
+        })
+    };
+    Some((node, index + 1))
+}
+
+fn parse_pseudo_arguments<'a>(
+    name: &str,
+    function: &ComponentValue<'a>,
+    input: &'a str,
+) -> Vec<Node<'a>> {
+    let args = trim_whitespace_slice(&function.children);
+    if args.is_empty() {
+        return comment_nodes(function.inner_comments.clone()).collect();
+    }
+    let loc = loc_of(args, function.loc);
+    let node = match name.to_ascii_lowercase().as_str() {
+        "not" | "is" | "where" | "has" | "matches" | "-moz-any" | "-webkit-any" | "host"
+        | "host-context" | "current" | "past" | "future" | "slotted" | "cue" | "global"
+        | "local" => parse_selector_list(args, loc, input),
+        "nth-child" | "nth-last-child" | "nth-of-type" | "nth-last-of-type" | "nth-col"
+        | "nth-last-col" => parse_nth(args, loc, input),
+        _ => None,
+    };
+    let mut children = vec![node.unwrap_or_else(|| raw(input, loc))];
+    children.extend(comment_nodes(function.inner_comments.clone()));
+    children
+}
+
+// `2n+1`, `odd`, `-n+3 of li.important`
+fn parse_nth<'a>(
+    values: &[ComponentValue<'a>],
+    loc: SourceLocation,
+    input: &'a str,
+) -> Option<Node<'a>> {
+    let of = values.iter().position(|v| v.is_ident("of"));
+    let (nth, selector) = match of {
+        Some(index) => (&values[..index], Some(&values[index + 1..])),
+        None => (values, None),
+    };
+    let nth = trim_whitespace_slice(nth);
+    let nth_loc = loc_of(nth, loc);
+    let nth = match nth {
+        [value] if value.is_ident("odd") || value.is_ident("even") => match value.token {
+            Token::Ident(name) => Node::Identifier(Identifier {
+                name: Cow::Borrowed(name),
+                loc: value.loc,
+            }),
+            _ => unreachable!(),
+        },
+        _ => {
+            let (a, b) = parse_an_plus_b(nth)?;
+            Node::AnPlusB(AnPlusB {
+                a: a.map(|a| Cow::Owned(a.to_string())),
+                b: b.map(|b| Cow::Owned(b.to_string())),
+                loc: nth_loc,
+            })
+        }
+    };
+    let selector = match selector {
+        Some(selector) => {
+            let selector = trim_whitespace_slice(selector);
+            Some(Box::new(parse_selector_list(
+                selector,
+                loc_of(selector, loc),
+                input,
+            )?))
+        }
+        None => None,
+    };
+    Some(Node::Nth(Nth {
+        nth: Box::new(nth),
+        selector,
+        loc,
+    }))
+}
+
+fn integer(numeric: &Numeric) -> Option<i64> {
+    if !numeric.is_integer() {
+        return None;
+    }
+    numeric.number.trim_start_matches('+').parse().ok()
+}
+
+// `n-3` => Some(-3)
+fn ndashdigit(s: &str) -> Option<i64> {
+    let digits = s
+        .strip_prefix('n')
+        .or_else(|| s.strip_prefix('N'))?
+        .strip_prefix('-')?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse::<i64>().ok().map(|b| -b)
+}
+
+// https://drafts.csswg.org/css-syntax-3/#anb-microsyntax
+// returns (A, B), A is `None` when there is no `n`, B is `None` when it's omitted
+pub(crate) fn parse_an_plus_b(values: &[ComponentValue]) -> Option<(Option<i64>, Option<i64>)> {
+    let first = values.first()?;
+    let rest = &values[1..];
+    match first.token {
+        Token::Number(numeric) if rest.is_empty() => Some((None, Some(integer(&numeric)?))),
+        Token::Dimension(numeric) => parse_n_unit(integer(&numeric)?, numeric.unit, rest),
+        Token::Ident(s) => {
+            if let Some(unit) = s.strip_prefix('-') {
+                return parse_n_unit(-1, unit, rest);
+            }
+            parse_n_unit(1, s, rest)
+        }
+        // no whitespace is allowed between `+` and `n`
+        Token::Delim("+") => match rest.first().map(|v| v.token) {
+            Some(Token::Ident(s)) if !s.starts_with('-') => parse_n_unit(1, s, &rest[1..]),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// the `n`, `n-` or `n-<digits>` part and what follows it
+fn parse_n_unit(a: i64, unit: &str, rest: &[ComponentValue]) -> Option<(Option<i64>, Option<i64>)> {
+    let rest = trim_whitespace_slice(rest);
+    if unit.eq_ignore_ascii_case("n") {
+        return Some((Some(a), parse_b(rest)?));
+    }
+    if unit.eq_ignore_ascii_case("n-") {
+        return match rest {
+            [value] => match value.token {
+                Token::Number(numeric) if !numeric.signed => {
+                    Some((Some(a), Some(-integer(&numeric)?)))
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+    }
+    if rest.is_empty() {
+        return Some((Some(a), Some(ndashdigit(unit)?)));
+    }
+    None
+}
+
+// the B part after `n`, `+3`, `- 3` or nothing
+fn parse_b(rest: &[ComponentValue]) -> Option<Option<i64>> {
+    let first = match rest.first() {
+        Some(first) => first,
+        None => return Some(None),
+    };
+    let sign = match first.token {
+        Token::Number(numeric) if numeric.signed && rest.len() == 1 => {
+            return Some(Some(integer(&numeric)?))
+        }
+        Token::Delim("+") => 1,
+        Token::Delim("-") => -1,
+        _ => return None,
+    };
+    match trim_whitespace_slice(&rest[1..]) {
+        [value] => match value.token {
+            Token::Number(numeric) if !numeric.signed => Some(Some(sign * integer(&numeric)?)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,133 @@
+#[allow(clippy::module_inception)]
+pub mod source_map;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// https://sourcemaps.info/spec.html#h.crcf4lqcxyxz
+// a signed integer as base64 VLQ, the sign is the lowest bit of the first digit
+pub fn encode_vlq(value: i64, out: &mut String) {
+    let mut vlq = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    } as u64;
+    loop {
+        let mut digit = (vlq & 0x1F) as usize;
+        vlq >>= 5;
+        if vlq > 0 {
+            // continuation bit
+            digit |= 0x20;
+        }
+        out.push(BASE64[digit] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+#[inline]
+fn base64_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'A'..=b'Z' => Some(byte - b'A'),
+        b'a'..=b'z' => Some(byte - b'a' + 26),
+        b'0'..=b'9' => Some(byte - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+// read a base64 VLQ from `input` at `position` and move past it
+pub fn decode_vlq(input: &[u8], position: &mut usize) -> Option<i64> {
+    let mut value: u64 = 0;
+    let mut shift = 0;
+    loop {
+        let digit = base64_value(*input.get(*position)?)? as u64;
+        *position += 1;
+        if shift > 60 {
+            return None;
+        }
+        value |= (digit & 0x1F) << shift;
+        shift += 5;
+        if digit & 0x20 == 0 {
+            break;
+        }
+    }
+    let magnitude = (value >> 1) as i64;
+    Some(if value & 1 == 1 {
+        -magnitude
+    } else {
+        magnitude
+    })
+}
+
+fn decode_base64(input: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(input.len() * 3 / 4);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for byte in input.bytes() {
+        if byte == b'=' || byte.is_ascii_whitespace() {
+            continue;
+        }
+        buffer = (buffer << 6) | base64_value(byte)? as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+fn decode_percent(input: &str) -> Vec<u8> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = input
+                .get(i + 1..i + 3)
+                .and_then(|h| u8::from_str_radix(h, 16).ok());
+            if let Some(byte) = hex {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    out
+}
+
+// the url of a `/*# sourceMappingURL=... */` comment, `/*@` is the old form
+pub fn source_mapping_url(comment: &str) -> Option<&str> {
+    let body = comment
+        .strip_prefix("/*# sourceMappingURL=")
+        .or_else(|| comment.strip_prefix("/*@ sourceMappingURL="))?;
+    let url = body.strip_suffix("*/").unwrap_or(body).trim();
+    if url.is_empty() {
+        return None;
+    }
+    Some(url)
+}
+
+// the JSON text of a `data:application/json` url, `None` for other urls
+pub fn decode_data_url(url: &str) -> Option<String> {
+    let rest = url.strip_prefix("data:")?;
+    let (media_type, data) = rest.split_once(',')?;
+    let mut params = media_type.split(';');
+    if !params
+        .next()?
+        .trim()
+        .eq_ignore_ascii_case("application/json")
+    {
+        return None;
+    }
+    let bytes = if params.any(|p| p.trim().eq_ignore_ascii_case("base64")) {
+        decode_base64(data)?
+    } else {
+        decode_percent(data)
+    };
+    String::from_utf8(bytes).ok()
+}
@@ -0,0 +1,545 @@
+use super::{decode_vlq, encode_vlq};
+use crate::parser::json::{parse_json, JsonError, JsonValue};
+use crate::parser::node::Node;
+use crate::tokenizer::source_location::{Position, SourceLocation};
+use crate::walker::walker::{walk, WalkAction};
+
+// one segment of `mappings`, lines and columns are zero-based
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mapping {
+    pub generated_line: usize,
+    pub generated_column: usize,
+    /// index into `sources`
+    pub source: usize,
+    pub original_line: usize,
+    pub original_column: usize,
+}
+
+/// a position in one of the `sources` of a map, lines and columns start at 1 like `Position`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OriginalPosition {
+    /// index into `sources`
+    pub source: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// where a location of the generated file comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OriginalLocation {
+    /// index into `sources`
+    pub source: usize,
+    pub start: OriginalPosition,
+    pub end: OriginalPosition,
+}
+
+// https://sourcemaps.info/spec.html
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SourceMap {
+    pub file: Option<String>,
+    pub sources: Vec<String>,
+    pub sources_content: Vec<Option<String>>,
+    pub mappings: Vec<Mapping>,
+    /// zero-based generated lines and columns of segments without an original position,
+    /// the generated text from there on maps to nothing until the next mapping
+    pub unmapped: Vec<(usize, usize)>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap::default()
+    }
+    /// index of a source, it is added when it's new
+    pub fn add_source(&mut self, source: &str, content: Option<&str>) -> usize {
+        if let Some(index) = self.sources.iter().position(|s| s == source) {
+            if self.sources_content[index].is_none() {
+                self.sources_content[index] = content.map(str::to_string);
+            }
+            return index;
+        }
+        self.sources.push(source.to_string());
+        self.sources_content.push(content.map(str::to_string));
+        self.sources.len() - 1
+    }
+    pub fn add_mapping(&mut self, mapping: Mapping) {
+        // only the first mapping of a generated position counts
+        if let Some(last) = self.mappings.last() {
+            if last.generated_line == mapping.generated_line
+                && last.generated_column == mapping.generated_column
+            {
+                return;
+            }
+        }
+        self.mappings.push(mapping);
+    }
+    /// the `mappings` field, segments are relative to the previous one
+    pub fn encode_mappings(&self) -> String {
+        // an unmapped segment is written before a mapping at the same position
+        let mut segments: Vec<(usize, usize, Option<&Mapping>)> = self
+            .unmapped
+            .iter()
+            .map(|&(line, column)| (line, column, None))
+            .chain(
+                self.mappings
+                    .iter()
+                    .map(|m| (m.generated_line, m.generated_column, Some(m))),
+            )
+            .collect();
+        segments.sort_by_key(|&(line, column, mapping)| (line, column, mapping.is_some()));
+        let mut out = String::new();
+        let mut line = 0;
+        let mut column = 0;
+        let mut source = 0;
+        let mut original_line = 0;
+        let mut original_column = 0;
+        for (i, &(generated_line, generated_column, mapping)) in segments.iter().enumerate() {
+            if generated_line != line {
+                while line < generated_line {
+                    out.push(';');
+                    line += 1;
+                }
+                column = 0;
+            } else if i > 0 {
+                out.push(',');
+            }
+            encode_vlq(generated_column as i64 - column as i64, &mut out);
+            column = generated_column;
+            let mapping = match mapping {
+                Some(mapping) => mapping,
+                None => continue,
+            };
+            encode_vlq(mapping.source as i64 - source as i64, &mut out);
+            encode_vlq(
+                mapping.original_line as i64 - original_line as i64,
+                &mut out,
+            );
+            encode_vlq(
+                mapping.original_column as i64 - original_column as i64,
+                &mut out,
+            );
+            source = mapping.source;
+            original_line = mapping.original_line;
+            original_column = mapping.original_column;
+        }
+        out
+    }
+    pub fn to_plain_object(&self) -> JsonValue {
+        let string = |s: &str| JsonValue::String(s.to_string());
+        let mut object = vec![("version".to_string(), JsonValue::Number(3.0))];
+        if let Some(file) = &self.file {
+            object.push(("file".to_string(), string(file)));
+        }
+        let sources = self.sources.iter().map(|s| string(s)).collect();
+        let contents = self
+            .sources_content
+            .iter()
+            .map(|c| c.as_deref().map_or(JsonValue::Null, string))
+            .collect();
+        object.push(("sources".to_string(), JsonValue::Array(sources)));
+        object.push(("sourcesContent".to_string(), JsonValue::Array(contents)));
+        object.push(("names".to_string(), JsonValue::Array(vec![])));
+        object.push(("mappings".to_string(), string(&self.encode_mappings())));
+        JsonValue::Object(object)
+    }
+    /// the source map as a JSON document
+    pub fn to_json(&self) -> String {
+        self.to_plain_object().stringify()
+    }
+    /// read a source map JSON document, e.g. one written by Sass
+    pub fn from_json(input: &str) -> Result<SourceMap, JsonError> {
+        let value = parse_json(input)?;
+        let error = |message: &str| {
+            Err(JsonError {
+                message: message.to_string(),
+            })
+        };
+        if value.get("sections").is_some() {
+            return error("Indexed source maps are not supported");
+        }
+        if value.get("version") != Some(&JsonValue::Number(3.0)) {
+            return error("Only version 3 source maps are supported");
+        }
+        let root = match value.get("sourceRoot") {
+            Some(JsonValue::String(root)) if !root.is_empty() && !root.ends_with('/') => {
+                format!("{}/", root)
+            }
+            Some(JsonValue::String(root)) => root.clone(),
+            _ => String::new(),
+        };
+        let sources: Vec<String> = match value.get("sources") {
+            Some(JsonValue::Array(sources)) => sources
+                .iter()
+                .map(|s| match s {
+                    JsonValue::String(s) => format!("{}{}", root, s),
+                    _ => String::new(),
+                })
+                .collect(),
+            _ => return error("`sources` of the source map is expected"),
+        };
+        let mut sources_content: Vec<Option<String>> = match value.get("sourcesContent") {
+            Some(JsonValue::Array(contents)) => contents
+                .iter()
+                .map(|c| match c {
+                    JsonValue::String(c) => Some(c.clone()),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        };
+        sources_content.resize(sources.len(), None);
+        let (mappings, unmapped) = match value.get("mappings") {
+            Some(JsonValue::String(mappings)) => decode_mappings(mappings, sources.len())?,
+            _ => return error("`mappings` of the source map is expected"),
+        };
+        let file = match value.get("file") {
+            Some(JsonValue::String(file)) => Some(file.clone()),
+            _ => None,
+        };
+        Ok(SourceMap {
+            file,
+            sources,
+            sources_content,
+            mappings,
+            unmapped,
+        })
+    }
+    /// where a position of the generated file comes from,
+    /// the closest mapping at or before it on the same line is used
+    pub fn original_position(&self, position: &Position) -> Option<OriginalPosition> {
+        let line = position.line.checked_sub(1)?;
+        let mapping = self.segment(line, position.column.saturating_sub(1))?;
+        Some(OriginalPosition {
+            source: mapping.source,
+            line: mapping.original_line + 1,
+            column: mapping.original_column + 1,
+        })
+    }
+    /// where a location of the generated file comes from, `None` when its start isn't mapped,
+    /// the end is as far from the mapping of the last character as in the generated file
+    pub fn original_location(&self, loc: &SourceLocation) -> Option<OriginalLocation> {
+        let start = self.original_position(&loc.start)?;
+        let line = loc.end.line.checked_sub(1)?;
+        let last = loc.end.column.checked_sub(2).and_then(|column| {
+            let mapping = self.segment(line, column)?;
+            let end = OriginalPosition {
+                source: mapping.source,
+                line: mapping.original_line + 1,
+                column: mapping.original_column + column - mapping.generated_column + 2,
+            };
+            (end.source == start.source && (end.line, end.column) >= (start.line, start.column))
+                .then_some(end)
+        });
+        Some(OriginalLocation {
+            source: start.source,
+            start,
+            end: last.unwrap_or(start),
+        })
+    }
+    // the mapping of a zero-based generated position, the closest one at or before it on
+    // the same line unless an unmapped segment comes after it
+    fn segment(&self, line: usize, column: usize) -> Option<&Mapping> {
+        let index = self
+            .mappings
+            .partition_point(|m| (m.generated_line, m.generated_column) <= (line, column));
+        let mapping = self.mappings[..index].last()?;
+        if mapping.generated_line != line {
+            return None;
+        }
+        let unmapped = self.unmapped.partition_point(|&u| u <= (line, column));
+        match self.unmapped[..unmapped].last() {
+            Some(&u) if u > (mapping.generated_line, mapping.generated_column) => None,
+            _ => Some(mapping),
+        }
+    }
+}
+
+/// the original locations of the nodes of a tree parsed from generated CSS, in walk order,
+/// nodes whose start isn't mapped are left out
+///
+/// source map columns count UTF-16 code units, so parse with `ColumnEncoding::Utf16`
+pub fn original_locations<'n, 'a>(
+    node: &'n Node<'a>,
+    map: &SourceMap,
+) -> Vec<(&'n Node<'a>, OriginalLocation)> {
+    let mut locations = vec![];
+    walk(node, |node, _| {
+        if let Some(loc) = map.original_location(node.loc()) {
+            locations.push((node, loc));
+        }
+        WalkAction::Continue
+    });
+    locations
+}
+
+// the `mappings` field back to absolute segments in generated order,
+// segments with only a generated column map to nothing and are kept apart
+type Segments = (Vec<Mapping>, Vec<(usize, usize)>);
+
+fn decode_mappings(input: &str, sources: usize) -> Result<Segments, JsonError> {
+    let bytes = input.as_bytes();
+    let mut mappings = vec![];
+    let mut unmapped = vec![];
+    let mut position = 0;
+    let mut line = 0;
+    let mut column = 0i64;
+    // source, original line and original column
+    let mut fields = [0i64; 3];
+    let error = |position: usize| JsonError {
+        message: format!("Invalid source map mappings at position {}", position),
+    };
+    while position < bytes.len() {
+        match bytes[position] {
+            b';' => {
+                line += 1;
+                column = 0;
+                position += 1;
+                continue;
+            }
+            b',' => {
+                position += 1;
+                continue;
+            }
+            _ => {}
+        }
+        let start = position;
+        column += decode_vlq(bytes, &mut position).ok_or_else(|| error(start))?;
+        let mut count = 0;
+        while position < bytes.len() && bytes[position] != b',' && bytes[position] != b';' {
+            let value = decode_vlq(bytes, &mut position).ok_or_else(|| error(start))?;
+            // the fifth field is an index into `names`, which isn't used
+            if count < 3 {
+                fields[count] += value;
+            }
+            count += 1;
+        }
+        if count == 0 {
+            if column < 0 {
+                return Err(error(start));
+            }
+            unmapped.push((line, column as usize));
+            continue;
+        }
+        let [source, original_line, original_column] = fields;
+        if count < 3
+            || column < 0
+            || source < 0
+            || source as usize >= sources
+            || original_line < 0
+            || original_column < 0
+        {
+            return Err(error(start));
+        }
+        mappings.push(Mapping {
+            generated_line: line,
+            generated_column: column as usize,
+            source: source as usize,
+            original_line: original_line as usize,
+            original_column: original_column as usize,
+        });
+    }
+    mappings.sort_by_key(|m| (m.generated_line, m.generated_column));
+    unmapped.sort();
+    Ok((mappings, unmapped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(
+        line: usize,
+        column: usize,
+        original_line: usize,
+        original_column: usize,
+    ) -> Mapping {
+        Mapping {
+            generated_line: line,
+            generated_column: column,
+            source: 0,
+            original_line,
+            original_column,
+        }
+    }
+
+    #[test]
+    fn encode_mappings() {
+        let mut map = SourceMap::new();
+        map.add_source("a.css", Some("a {}"));
+        map.add_mapping(mapping(0, 0, 0, 0));
+        map.add_mapping(mapping(0, 2, 0, 4));
+        map.add_mapping(mapping(0, 2, 0, 8));
+        map.add_mapping(mapping(2, 1, 5, 0));
+        assert_eq!(map.encode_mappings(), "AAAA,EAAI;;CAKJ");
+        assert_eq!(
+            map.to_json(),
+            r#"{"version":3,"sources":["a.css"],"sourcesContent":["a {}"],"names":[],"mappings":"AAAA,EAAI;;CAKJ"}"#
+        );
+    }
+
+    #[test]
+    fn encode_vlq_values() {
+        let encode = |value| {
+            let mut out = String::new();
+            encode_vlq(value, &mut out);
+            out
+        };
+        assert_eq!(encode(0), "A");
+        assert_eq!(encode(-1), "D");
+        assert_eq!(encode(15), "e");
+        assert_eq!(encode(16), "gB");
+        assert_eq!(encode(-1000), "x+B");
+    }
+
+    #[test]
+    fn decode_source_map() {
+        let mut map = SourceMap::new();
+        map.file = Some("a.css".to_string());
+        map.add_source("a.scss", Some(".a { .b {} }"));
+        map.add_mapping(mapping(0, 0, 0, 0));
+        map.add_mapping(mapping(0, 3, 0, 5));
+        map.add_mapping(mapping(2, 1, 5, 0));
+        assert_eq!(SourceMap::from_json(&map.to_json()), Ok(map.clone()));
+
+        let position = |line, column| Position {
+            line,
+            column,
+            offset: 0,
+        };
+        let original = |line, column| {
+            Some(OriginalPosition {
+                source: 0,
+                line,
+                column,
+            })
+        };
+        assert_eq!(map.original_position(&position(1, 1)), original(1, 1));
+        assert_eq!(map.original_position(&position(1, 3)), original(1, 1));
+        assert_eq!(map.original_position(&position(1, 4)), original(1, 6));
+        assert_eq!(map.original_position(&position(2, 1)), None);
+        assert_eq!(map.original_position(&position(3, 1)), None);
+        assert_eq!(map.original_position(&position(3, 9)), original(6, 1));
+    }
+
+    #[test]
+    fn keep_unmapped_segments() {
+        let map = SourceMap::from_json(
+            r#"{"version":3,"sources":["a.css"],"mappings":"AAAA,EAAE,E,EAAE;AACA"}"#,
+        )
+        .unwrap();
+        assert_eq!(map.unmapped, vec![(0, 4)]);
+        let original = |line, column| {
+            map.original_position(&Position {
+                line,
+                column,
+                offset: 0,
+            })
+            .map(|p| (p.line, p.column))
+        };
+        assert_eq!(original(1, 4), Some((1, 3)));
+        assert_eq!(original(1, 5), None);
+        assert_eq!(original(1, 6), None);
+        assert_eq!(original(1, 7), Some((1, 5)));
+        assert_eq!(original(2, 9), Some((2, 5)));
+    }
+
+    #[test]
+    fn remap_node_locations() {
+        use crate::parser::parser::{Parser, ParserOptions};
+        use crate::tokenizer::source_location::ColumnEncoding;
+
+        // `a {\n  color: red;\n}` minified to `a{color:red}`
+        let mut map = SourceMap::new();
+        map.add_source("a.css", None);
+        map.add_mapping(mapping(0, 0, 0, 0));
+        map.add_mapping(mapping(0, 2, 1, 2));
+        map.unmapped.push((0, 11));
+        let result = Parser::new(
+            "a{color:red}",
+            ParserOptions {
+                column_encoding: ColumnEncoding::Utf16,
+                ..Default::default()
+            },
+        )
+        .parse();
+        let locations = original_locations(&result.node, &map);
+        let declaration = locations
+            .iter()
+            .find(|(node, _)| matches!(node, Node::Declaration(_)))
+            .map(|(_, loc)| {
+                (
+                    (loc.start.line, loc.start.column),
+                    (loc.end.line, loc.end.column),
+                )
+            });
+        assert_eq!(declaration, Some(((2, 3), (2, 12))));
+        assert!(locations.len() > 1);
+        assert!(locations.iter().all(|(_, loc)| loc.source == 0));
+    }
+
+    #[test]
+    fn decode_source_map_fields() {
+        let map = SourceMap::from_json(
+            r#"{"version":3,"sourceRoot":"src","sources":["a.scss","b.scss"],"sourcesContent":["a {}"],"mappings":"A,AAAA,CCCC;;"}"#,
+        )
+        .unwrap();
+        assert_eq!(map.sources, vec!["src/a.scss", "src/b.scss"]);
+        assert_eq!(map.sources_content, vec![Some("a {}".to_string()), None]);
+        assert_eq!(
+            map.mappings,
+            vec![
+                mapping(0, 0, 0, 0),
+                Mapping {
+                    source: 1,
+                    ..mapping(0, 1, 1, 1)
+                }
+            ]
+        );
+        assert_eq!(map.unmapped, vec![(0, 0)]);
+        assert_eq!(map.encode_mappings(), "A,AAAA,CCCC");
+
+        let message = |json| SourceMap::from_json(json).unwrap_err().message;
+        assert_eq!(
+            message(r#"{"version":3,"sections":[]}"#),
+            "Indexed source maps are not supported"
+        );
+        assert_eq!(
+            message(r#"{"version":2,"sources":[],"mappings":""}"#),
+            "Only version 3 source maps are supported"
+        );
+        assert_eq!(
+            message(r#"{"version":3,"sources":["a"],"mappings":"AACA,!"}"#),
+            "Invalid source map mappings at position 5"
+        );
+        assert_eq!(
+            message(r#"{"version":3,"sources":[],"mappings":"AAAA"}"#),
+            "Invalid source map mappings at position 0"
+        );
+    }
+
+    #[test]
+    fn read_source_mapping_url() {
+        use crate::source_map::{decode_data_url, source_mapping_url};
+
+        assert_eq!(
+            source_mapping_url("/*# sourceMappingURL=a.css.map */"),
+            Some("a.css.map")
+        );
+        assert_eq!(
+            source_mapping_url("/*@ sourceMappingURL=a.css.map*/"),
+            Some("a.css.map")
+        );
+        assert_eq!(source_mapping_url("/* sourceMappingURL=a.css.map */"), None);
+        assert_eq!(source_mapping_url("/*# sourceMappingURL= */"), None);
+
+        assert_eq!(
+            decode_data_url("data:application/json;charset=utf-8;base64,eyJhIjoxfQ==").as_deref(),
+            Some(r#"{"a":1}"#)
+        );
+        assert_eq!(
+            decode_data_url("data:application/json,%7B%22a%22%3A1%7D").as_deref(),
+            Some(r#"{"a":1}"#)
+        );
+        assert_eq!(decode_data_url("data:text/plain,a"), None);
+        assert_eq!(decode_data_url("a.css.map"), None);
+    }
+}
@@ -0,0 +1,219 @@
+use std::borrow::Cow;
+
+// https://encoding.spec.whatwg.org/#names-and-labels
+/// the encodings a stylesheet can be decoded from, ISO-8859-1 and ASCII are read as windows-1252
+/// like browsers do
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Windows1252,
+}
+
+impl Encoding {
+    // https://encoding.spec.whatwg.org/#concept-encoding-get
+    /// the encoding of a label like `"utf-8"` or `"latin1"`, `None` for unknown and unsupported ones
+    pub fn for_label(label: &str) -> Option<Encoding> {
+        let label = label
+            .trim_matches(['\t', '\n', '\x0C', '\r', ' '])
+            .to_ascii_lowercase();
+        match label.as_str() {
+            "unicode-1-1-utf-8" | "unicode11utf8" | "unicode20utf8" | "utf-8" | "utf8"
+            | "x-unicode20utf8" => Some(Encoding::Utf8),
+            "unicodefffe" | "utf-16be" => Some(Encoding::Utf16Be),
+            "csunicode" | "iso-10646-ucs-2" | "ucs-2" | "unicode" | "unicodefeff" | "utf-16"
+            | "utf-16le" => Some(Encoding::Utf16Le),
+            "ansi_x3.4-1968" | "ascii" | "cp1252" | "cp819" | "csisolatin1" | "ibm819"
+            | "iso-8859-1" | "iso-ir-100" | "iso8859-1" | "iso88591" | "iso_8859-1"
+            | "iso_8859-1:1987" | "l1" | "latin1" | "us-ascii" | "windows-1252" | "x-cp1252" => {
+                Some(Encoding::Windows1252)
+            }
+            _ => None,
+        }
+    }
+    /// decode `bytes` without looking for a BOM, invalid sequences and lone surrogates
+    /// become U+FFFD, the text is borrowed when the bytes are already valid UTF-8 text
+    pub fn decode(self, bytes: &[u8]) -> Cow<'_, str> {
+        match self {
+            Encoding::Utf8 => String::from_utf8_lossy(bytes),
+            Encoding::Utf16Le => decode_utf16(bytes, u16::from_le_bytes),
+            Encoding::Utf16Be => decode_utf16(bytes, u16::from_be_bytes),
+            Encoding::Windows1252 => decode_windows_1252(bytes),
+        }
+    }
+}
+
+// https://encoding.spec.whatwg.org/#bom-sniff
+fn bom(bytes: &[u8]) -> Option<(Encoding, usize)> {
+    if bytes.starts_with(b"\xEF\xBB\xBF") {
+        Some((Encoding::Utf8, 3))
+    } else if bytes.starts_with(b"\xFE\xFF") {
+        Some((Encoding::Utf16Be, 2))
+    } else if bytes.starts_with(b"\xFF\xFE") {
+        Some((Encoding::Utf16Le, 2))
+    } else {
+        None
+    }
+}
+
+// the label of a `@charset "...";` rule at the very start of the first 1024 bytes
+fn charset_label(bytes: &[u8]) -> Option<&[u8]> {
+    let head = &bytes[..bytes.len().min(1024)];
+    let rest = head.strip_prefix(b"@charset \"")?;
+    let end = rest.iter().position(|b| *b == b'"' || *b == b';')?;
+    if rest[end..].starts_with(b"\";") {
+        Some(&rest[..end])
+    } else {
+        None
+    }
+}
+
+// https://drafts.csswg.org/css-syntax-3/#determine-the-fallback-encoding
+/// the encoding of a stylesheet without a BOM, from its `@charset "...";` rule or else the
+/// encoding of the environment, like the referring document, or else UTF-8
+pub fn fallback_encoding(bytes: &[u8], environment: Option<Encoding>) -> Encoding {
+    let label = charset_label(bytes).and_then(|label| std::str::from_utf8(label).ok());
+    match label.and_then(Encoding::for_label) {
+        // a stylesheet that can read its `@charset` isn't UTF-16
+        Some(Encoding::Utf16Le) | Some(Encoding::Utf16Be) => Encoding::Utf8,
+        Some(encoding) => encoding,
+        None => environment.unwrap_or(Encoding::Utf8),
+    }
+}
+
+/// a stylesheet decoded to text, `Tokenizer::from_decoded` and `Parser::from_decoded` read it
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Decoded<'b> {
+    pub text: Cow<'b, str>,
+    pub encoding: Encoding,
+    /// the label of a `@charset` rule naming an unknown or unsupported encoding,
+    /// the text was decoded with the encoding of the environment or UTF-8 instead
+    pub unsupported_charset: Option<String>,
+}
+
+// https://drafts.csswg.org/css-syntax-3/#input-byte-stream
+/// decode a stylesheet to text for the tokenizer, a BOM decides the encoding and is removed,
+/// without one the fallback encoding is used
+pub fn decode(bytes: &[u8], environment: Option<Encoding>) -> Decoded<'_> {
+    if let Some((encoding, len)) = bom(bytes) {
+        return Decoded {
+            text: encoding.decode(&bytes[len..]),
+            encoding,
+            unsupported_charset: None,
+        };
+    }
+    let encoding = fallback_encoding(bytes, environment);
+    let unsupported_charset = charset_label(bytes)
+        .filter(|label| {
+            let label = std::str::from_utf8(label).ok();
+            label.and_then(Encoding::for_label).is_none()
+        })
+        .map(|label| String::from_utf8_lossy(label).into_owned());
+    Decoded {
+        text: encoding.decode(bytes),
+        encoding,
+        unsupported_charset,
+    }
+}
+
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> Cow<'static, str> {
+    let chunks = bytes.chunks_exact(2);
+    let odd = !chunks.remainder().is_empty();
+    let units = chunks.map(|pair| unit([pair[0], pair[1]]));
+    let mut out: String = char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+    // a truncated code unit at the end
+    if odd {
+        out.push(char::REPLACEMENT_CHARACTER);
+    }
+    Cow::Owned(out)
+}
+
+// https://encoding.spec.whatwg.org/index-windows-1252.txt
+// the code points of the bytes 0x80 to 0x9F, the other bytes are the same as in ISO-8859-1
+const WINDOWS_1252: [u16; 32] = [
+    0x20AC, 0x0081, 0x201A, 0x0192, 0x201E, 0x2026, 0x2020, 0x2021, 0x02C6, 0x2030, 0x0160, 0x2039,
+    0x0152, 0x008D, 0x017D, 0x008F, 0x0090, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014,
+    0x02DC, 0x2122, 0x0161, 0x203A, 0x0153, 0x009D, 0x017E, 0x0178,
+];
+
+fn decode_windows_1252(bytes: &[u8]) -> Cow<'_, str> {
+    if bytes.is_ascii() {
+        // ASCII is valid UTF-8
+        return Cow::Borrowed(std::str::from_utf8(bytes).unwrap());
+    }
+    let out = bytes
+        .iter()
+        .map(|&byte| match byte {
+            0x80..=0x9F => char::from_u32(WINDOWS_1252[byte as usize - 0x80] as u32).unwrap(),
+            _ => byte as char,
+        })
+        .collect();
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::tokenizer::Tokenizer;
+
+    #[test]
+    fn determine_encoding() {
+        let decoded = decode(b"\xEF\xBB\xBFa{}", None);
+        assert_eq!(
+            (decoded.text, decoded.encoding),
+            (Cow::Borrowed("a{}"), Encoding::Utf8)
+        );
+        let decoded = decode(b"\xFF\xFEa\0{\0}\0", Some(Encoding::Windows1252));
+        assert_eq!(
+            (decoded.text, decoded.encoding),
+            (Cow::Borrowed("a{}"), Encoding::Utf16Le)
+        );
+        assert_eq!(
+            decode(b"\xFE\xFF\0a\xD8\x3D\xDE\x00\xD8\x00\0", None).text,
+            "a\u{1F600}\u{FFFD}\u{FFFD}"
+        );
+        // the BOM wins over `@charset`
+        assert_eq!(
+            decode(b"\xEF\xBB\xBF@charset \"latin1\"; \xC3\xA9", None).text,
+            "@charset \"latin1\"; \u{E9}"
+        );
+
+        let decoded = decode(b"@charset \"ISO-8859-1\"; \x80\xE9", None);
+        assert_eq!(decoded.encoding, Encoding::Windows1252);
+        assert_eq!(decoded.text, "@charset \"ISO-8859-1\"; \u{20AC}\u{E9}");
+        assert_eq!(decoded.unsupported_charset, None);
+        assert_eq!(
+            fallback_encoding(b"@charset \"utf-16\";", None),
+            Encoding::Utf8
+        );
+        // `@charset` must be written exactly like this
+        assert_eq!(
+            fallback_encoding(b"@charset 'latin1';", Some(Encoding::Utf16Be)),
+            Encoding::Utf16Be
+        );
+        assert_eq!(
+            fallback_encoding(b"@charset \"latin1\" ;", None),
+            Encoding::Utf8
+        );
+        assert_eq!(
+            fallback_encoding(b"@charset \"koi8-r\";", None),
+            Encoding::Utf8
+        );
+        let decoded = decode(b"@charset \"koi8-r\"; a{}", Some(Encoding::Windows1252));
+        assert_eq!(decoded.encoding, Encoding::Windows1252);
+        assert_eq!(decoded.unsupported_charset.as_deref(), Some("koi8-r"));
+        assert_eq!(Tokenizer::from_decoded(&decoded).count(), 8);
+        assert_eq!(
+            fallback_encoding(b"a{}", Some(Encoding::Windows1252)),
+            Encoding::Windows1252
+        );
+        assert_eq!(decode(b"a\xFFb", None).text, "a\u{FFFD}b");
+        assert_eq!(
+            Encoding::for_label(" Latin1\n"),
+            Some(Encoding::Windows1252)
+        );
+    }
+}
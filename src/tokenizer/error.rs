@@ -0,0 +1,49 @@
+use crate::tokenizer::source_location::Position;
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum TokenizerErrorKind {
+    EOFInComment,
+    EOFInString,
+    NewlineInString,
+    EOFInEscape,
+    InvalidEscape,
+    EOFInUrl,
+    BadUrlCharacter,
+}
+
+impl TokenizerErrorKind {
+    pub fn message(&self) -> &'static str {
+        match self {
+            TokenizerErrorKind::EOFInComment => "Unexpected end of input in comment",
+            TokenizerErrorKind::EOFInString => "Unexpected end of input in string",
+            TokenizerErrorKind::NewlineInString => "Unexpected newline in string",
+            TokenizerErrorKind::EOFInEscape => "Unexpected end of input in escape",
+            TokenizerErrorKind::InvalidEscape => "Invalid escape",
+            TokenizerErrorKind::EOFInUrl => "Unexpected end of input in url",
+            TokenizerErrorKind::BadUrlCharacter => "Unexpected character in url",
+        }
+    }
+}
+
+// https://drafts.csswg.org/css-syntax-3/#tokenization
+/// a parse error of the tokenizer, the token is still produced, e.g. as a bad-string token
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct TokenizerError {
+    pub kind: TokenizerErrorKind,
+    pub position: Position,
+}
+
+impl fmt::Display for TokenizerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}:{})",
+            self.kind.message(),
+            self.position.line,
+            self.position.column
+        )
+    }
+}
+
+pub type TokenizerErrorCallback = Box<dyn FnMut(&TokenizerError)>;
@@ -1,28 +1,37 @@
+pub mod encoding;
+pub mod error;
+mod scan;
 pub mod source_location;
 pub mod token;
+#[allow(clippy::module_inception)]
 pub mod tokenizer;
 
+use std::borrow::Cow;
 
 // A code point between U+0030 DIGIT ZERO (0) and U+0039 DIGIT NINE (9) inclusive.
 #[inline]
+#[allow(clippy::manual_range_contains)]
 pub fn is_digit(code: u8) -> bool {
     code >= b'0' && code <= b'9'
 }
 
 // A digit, or a code point between U+0041 LATIN CAPITAL LETTER A (A) and U+0046 LATIN CAPITAL LETTER F (F) inclusive, or a code point between U+0061 LATIN SMALL LETTER A (a) and U+0066 LATIN SMALL LETTER F (f) inclusive.
 #[inline]
+#[allow(clippy::manual_range_contains)]
 pub fn is_hex_digit(code: u8) -> bool {
     is_digit(code) || (code >= b'A' && code <= b'F') || (code >= b'a' && code <= b'f')
 }
 
 // A code point between U+0041 LATIN CAPITAL LETTER A (A) and U+005A LATIN CAPITAL LETTER Z (Z).
 #[inline]
+#[allow(clippy::manual_range_contains)]
 pub fn is_uppercase_letter(code: u8) -> bool {
     code >= b'A' && code <= b'Z'
 }
 
 // A code point between U+0061 LATIN SMALL LETTER A (a) and U+007A LATIN SMALL LETTER Z (z).
 #[inline]
+#[allow(clippy::manual_range_contains)]
 pub fn is_lowercase_ltter(code: u8) -> bool {
     code >= b'a' && code <= b'z'
 }
@@ -52,8 +61,9 @@ pub fn is_identifier(code: u8) -> bool {
 }
 // A code point between U+0000 NULL and U+0008 BACKSPACE inclusive, or U+000B LINE TABULATION, or a code point between U+000E SHIFT OUT and U+001F INFORMATION SEPARATOR ONE inclusive, or U+007F DELETE.
 #[inline]
+#[allow(clippy::manual_range_contains)]
 pub fn is_non_printable(code: u8) -> bool {
-    code <= 0x0008 || code == 0x000B || (code >= 0x000E && code == 0x001F) || code == 0x007F
+    code <= 0x0008 || code == 0x000B || (code >= 0x000E && code <= 0x001F) || code == 0x007F
 }
 // U+000A LINE FEED. Note that U+000D CARRIAGE RETURN and U+000C FORM FEED are not included in this definition, as they are converted to U+000A LINE FEED during preprocessing.
 #[inline]
@@ -96,6 +106,98 @@ pub fn would_start_a_number(first: u8, second: u8, third: u8) -> bool {
     is_digit(first)
 }
 
+// https://drafts.csswg.org/css-syntax-3/#starts-a-unicode-range
+pub fn would_start_a_unicode_range(first: u8, second: u8, third: u8) -> bool {
+    (first == b'U' || first == b'u') && second == b'+' && (third == b'?' || is_hex_digit(third))
+}
+
+// https://drafts.csswg.org/css-syntax-3/#input-preprocessing
+/// the input with `\r\n`, `\r` and form feeds replaced by `\n` and U+0000 by U+FFFD,
+/// borrowed when there is nothing to replace
+///
+/// the tokenizer reads the input this way without copying it, tokens and lines are the same for
+/// a preprocessed input but offsets and the source text of whitespace and comments differ
+pub fn preprocess(input: &str) -> Cow<'_, str> {
+    if !input.contains(['\r', '\x0C', '\0']) {
+        return Cow::Borrowed(input);
+    }
+    let mut out = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' => {
+                chars.next_if_eq(&'\n');
+                out.push('\n');
+            }
+            '\x0C' => out.push('\n'),
+            '\0' => out.push('\u{FFFD}'),
+            _ => out.push(c),
+        }
+    }
+    Cow::Owned(out)
+}
+
+// https://drafts.csswg.org/css-syntax-3/#consume-escaped-code-point
+// decode the escapes of a token, `text` is borrowed when there are none,
+// in a string an escaped newline is removed and a backslash at the end is dropped,
+// U+0000 is replaced like in preprocessing
+pub fn decode_escapes(text: &str, in_string: bool) -> Cow<'_, str> {
+    if !text.contains(['\\', '\0']) {
+        return Cow::Borrowed(text);
+    }
+    let bytes = text.as_bytes();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while let Some(found) = text[i..].find(['\\', '\0']) {
+        out.push_str(&text[i..i + found]);
+        i += found + 1;
+        if bytes[i - 1] == 0 {
+            out.push('\u{FFFD}');
+            continue;
+        }
+        let next = match text[i..].chars().next() {
+            Some(c) => c,
+            None => {
+                if !in_string {
+                    out.push('\u{FFFD}');
+                }
+                break;
+            }
+        };
+        if next.is_ascii_hexdigit() {
+            let len = bytes[i..]
+                .iter()
+                .take(6)
+                .take_while(|b| is_hex_digit(**b))
+                .count();
+            let code = u32::from_str_radix(&text[i..i + len], 16).unwrap_or(0);
+            i += len;
+            // zero, surrogates and code points past U+10FFFF
+            let c = match char::from_u32(code) {
+                Some(c) if code != 0 => c,
+                _ => '\u{FFFD}',
+            };
+            out.push(c);
+            if bytes.get(i) == Some(&b'\r') && bytes.get(i + 1) == Some(&b'\n') {
+                i += 2;
+            } else if bytes.get(i).is_some_and(|b| is_whitespace(*b)) {
+                i += 1;
+            }
+        } else if in_string && is_newline(bytes[i]) {
+            i += if bytes[i..].starts_with(b"\r\n") {
+                2
+            } else {
+                1
+            };
+        } else {
+            out.push(if next == '\0' { '\u{FFFD}' } else { next });
+            i += next.len_utf8();
+        }
+    }
+    out.push_str(&text[i..]);
+    Cow::Owned(out)
+}
+
 #[inline]
 pub fn utf8_is_cont_byte(byte: u8) -> bool {
     (byte & !0b0011_1111) == 0b1000_0000
@@ -0,0 +1,157 @@
+// word-at-a-time scanning, eight bytes are checked at once with the bit tricks from
+// https://graphics.stanford.edu/~seander/bithacks.html#ValueInWord
+// https://graphics.stanford.edu/~seander/bithacks.html#HasBetweenInWord
+
+const ONES: u64 = 0x0101_0101_0101_0101;
+const HIGH_BITS: u64 = 0x8080_8080_8080_8080;
+
+#[inline]
+fn splat(byte: u8) -> u64 {
+    ONES * byte as u64
+}
+
+// the high bit of every zero byte of `word` is set, a byte above a zero byte may be
+// flagged as well, so only the lowest flagged byte is exact
+#[inline]
+fn zero_bytes(word: u64) -> u64 {
+    word.wrapping_sub(ONES) & !word & HIGH_BITS
+}
+
+#[inline]
+fn word(chunk: &[u8]) -> u64 {
+    u64::from_le_bytes([
+        chunk[0], chunk[1], chunk[2], chunk[3], chunk[4], chunk[5], chunk[6], chunk[7],
+    ])
+}
+
+// the high bit of every byte of `ascii` that is at least `low`, the high bits of `ascii`
+// must be clear so no sum carries into the next byte
+#[inline]
+fn at_least(ascii: u64, low: u8) -> u64 {
+    (ascii + splat(0x80 - low)) & HIGH_BITS
+}
+
+#[inline]
+fn in_range(ascii: u64, low: u8, high: u8) -> u64 {
+    at_least(ascii, low) & !at_least(ascii, high + 1)
+}
+
+// the high bit of every identifier byte of `word`, see `IDENTIFIER`
+#[inline]
+fn identifier_bytes(word: u64) -> u64 {
+    let ascii = word & !HIGH_BITS;
+    // upper case letters become lower case ones and nothing else does
+    let letters = in_range(ascii | splat(0x20), b'a', b'z');
+    let digits = in_range(ascii, b'0', b'9');
+    let others = in_range(ascii, b'-', b'-') | in_range(ascii, b'_', b'_') | in_range(ascii, 0, 0);
+    (word & HIGH_BITS) | letters | digits | others
+}
+
+/// the index of the first byte of `bytes` that is one of `needles`
+#[inline]
+pub fn find_any(bytes: &[u8], needles: &[u8]) -> Option<usize> {
+    if cfg!(feature = "byte-scan") {
+        return bytewise::find_any(bytes, needles);
+    }
+    let mut chunks = bytes.chunks_exact(8);
+    let mut offset = 0;
+    for chunk in &mut chunks {
+        let word = word(chunk);
+        let found = needles
+            .iter()
+            .fold(0, |found, needle| found | zero_bytes(word ^ splat(*needle)));
+        if found != 0 {
+            return Some(offset + (found.trailing_zeros() / 8) as usize);
+        }
+        offset += 8;
+    }
+    bytewise::find_any(chunks.remainder(), needles).map(|i| offset + i)
+}
+
+/// the length of the run of identifier bytes at the start of `bytes`
+#[inline]
+pub fn identifier_len(bytes: &[u8]) -> usize {
+    if cfg!(feature = "byte-scan") {
+        return bytewise::identifier_len(bytes);
+    }
+    let mut chunks = bytes.chunks_exact(8);
+    let mut offset = 0;
+    for chunk in &mut chunks {
+        let stops = !identifier_bytes(word(chunk)) & HIGH_BITS;
+        if stops != 0 {
+            return offset + (stops.trailing_zeros() / 8) as usize;
+        }
+        offset += 8;
+    }
+    offset + bytewise::identifier_len(chunks.remainder())
+}
+
+// the byte-at-a-time scan, it reads the ends of the input, the tokenizer uses it for
+// everything with the `byte-scan` feature so `cargo bench` can compare the two
+mod bytewise {
+    // the bytes of an identifier, non-ASCII and U+0000 which is read as U+FFFD included,
+    // escapes are left to the tokenizer
+    const IDENTIFIER: [bool; 256] = {
+        let mut table = [false; 256];
+        let mut i = 0;
+        while i < 256 {
+            let byte = i as u8;
+            table[i] = byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'-' || byte >= 0x80;
+            i += 1;
+        }
+        table[0] = true;
+        table
+    };
+
+    #[inline]
+    pub fn find_any(bytes: &[u8], needles: &[u8]) -> Option<usize> {
+        bytes.iter().position(|byte| needles.contains(byte))
+    }
+
+    #[inline]
+    pub fn identifier_len(bytes: &[u8]) -> usize {
+        bytes
+            .iter()
+            .position(|byte| !IDENTIFIER[*byte as usize])
+            .unwrap_or(bytes.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_words() {
+        let text = b"0123456789abcdef*/ghij";
+        assert_eq!(find_any(text, b"*"), Some(16));
+        assert_eq!(find_any(text, b"/a"), Some(10));
+        assert_eq!(find_any(text, b"j"), Some(21));
+        assert_eq!(find_any(text, b"x"), None);
+        // a byte above a match is flagged too
+        assert_eq!(
+            find_any(b"\x01\x00\x01\x01\x01\x01\x01\x01", b"\x01"),
+            Some(0)
+        );
+        assert_eq!(find_any(b"\x02\x01\x00\x00", b"\x00"), Some(2));
+        assert_eq!(identifier_len("a-b_ü\0c d".as_bytes()), 8);
+        assert_eq!(identifier_len(b"abc"), 3);
+    }
+
+    #[test]
+    fn scan_like_bytewise() {
+        for byte in 0..=255u8 {
+            for len in 0..12 {
+                let mut text = vec![b'a'; len];
+                text.extend_from_slice(&[byte, b'x', b'(', b'y']);
+                assert_eq!(
+                    identifier_len(&text),
+                    bytewise::identifier_len(&text),
+                    "{}",
+                    byte
+                );
+                assert_eq!(find_any(&text, &[byte]), bytewise::find_any(&text, &[byte]));
+            }
+        }
+    }
+}
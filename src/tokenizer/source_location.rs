@@ -1,5 +1,11 @@
+use super::utf8_is_cont_byte;
+use std::ops::Range;
+
+/// a place in the input, `offset` is in bytes, `line` and `column` start at 1,
+/// what a column counts depends on the `ColumnEncoding`
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub struct Position {
+    pub offset: usize,
     pub line: usize,
     pub column: usize,
 }
@@ -9,3 +15,200 @@ pub struct SourceLocation {
     pub start: Position,
     pub end: Position,
 }
+
+impl Position {
+    /// the position at the end of `text`, which starts at this position
+    pub fn advance(self, text: &str, column_encoding: ColumnEncoding) -> Position {
+        let bytes = text.as_bytes();
+        let mut line = self.line;
+        let mut line_start = None;
+        for (i, &byte) in bytes.iter().enumerate() {
+            if byte == b'\n' || byte == 0x0C || (byte == b'\r' && bytes.get(i + 1) != Some(&b'\n'))
+            {
+                line += 1;
+                line_start = Some(i + 1);
+            }
+        }
+        let column = match line_start {
+            Some(start) => column_encoding.columns(&text[start..]) + 1,
+            None => self.column + column_encoding.columns(text),
+        };
+        Position {
+            offset: self.offset + text.len(),
+            line,
+            column,
+        }
+    }
+}
+
+impl SourceLocation {
+    /// the byte range of the location, `&input[loc.range()]` is its source text
+    pub fn range(&self) -> Range<usize> {
+        self.start.offset..self.end.offset
+    }
+}
+
+/// the unit of `Position::column`
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum ColumnEncoding {
+    /// UTF-8 bytes, a column is a byte offset in the line
+    Utf8,
+    /// UTF-16 code units like in the Language Server Protocol and JavaScript
+    Utf16,
+    /// Unicode code points
+    #[default]
+    CodePoints,
+}
+
+impl ColumnEncoding {
+    /// the number of columns of `text`
+    #[inline]
+    pub fn columns(self, text: &str) -> usize {
+        if text.is_ascii() {
+            return text.len();
+        }
+        match self {
+            ColumnEncoding::Utf8 => text.len(),
+            ColumnEncoding::Utf16 => text.chars().map(char::len_utf16).sum(),
+            ColumnEncoding::CodePoints => text.chars().count(),
+        }
+    }
+    // the columns of a single byte of UTF-8 text, a character counts at its first byte
+    #[inline]
+    pub(crate) fn byte_columns(self, byte: u8) -> usize {
+        match self {
+            ColumnEncoding::Utf8 => 1,
+            _ if utf8_is_cont_byte(byte) => 0,
+            // four bytes in UTF-8 are a surrogate pair in UTF-16
+            ColumnEncoding::Utf16 if byte >= 0xF0 => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// converts between byte offsets and lines and columns of a text,
+/// lines end at `\n`, `\r\n`, `\r` or a form feed like in the tokenizer
+pub struct LineIndex<'a> {
+    input: &'a str,
+    // the offset of the first byte of every line
+    line_starts: Vec<usize>,
+    column_encoding: ColumnEncoding,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(input: &'a str) -> LineIndex<'a> {
+        LineIndex::with_column_encoding(input, ColumnEncoding::default())
+    }
+    pub fn with_column_encoding(input: &'a str, column_encoding: ColumnEncoding) -> LineIndex<'a> {
+        let bytes = input.as_bytes();
+        let mut line_starts = vec![0];
+        for (i, &byte) in bytes.iter().enumerate() {
+            if byte == b'\n' || byte == 0x0C || (byte == b'\r' && bytes.get(i + 1) != Some(&b'\n'))
+            {
+                line_starts.push(i + 1);
+            }
+        }
+        LineIndex {
+            input,
+            line_starts,
+            column_encoding,
+        }
+    }
+    /// the position of a byte offset, an offset past the end or inside a character is moved back
+    pub fn position(&self, offset: usize) -> Position {
+        let mut offset = offset.min(self.input.len());
+        while !self.input.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let column = self
+            .column_encoding
+            .columns(&self.input[self.line_starts[line]..offset])
+            + 1;
+        Position {
+            offset,
+            line: line + 1,
+            column,
+        }
+    }
+    /// the byte offset of a line and column, a column past the end of the line gives the end of the line
+    /// and a column inside a character the next character
+    pub fn offset(&self, line: usize, column: usize) -> Option<usize> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self
+            .line_starts
+            .get(line)
+            .map_or(self.input.len(), |next| *next);
+        let text = self.input[start..end].trim_end_matches(['\n', '\r', '\x0C']);
+        let target = column.checked_sub(1)?;
+        let mut columns = 0;
+        for (i, c) in text.char_indices() {
+            if columns >= target {
+                return Some(start + i);
+            }
+            columns += self.column_encoding.columns(c.encode_utf8(&mut [0; 4]));
+        }
+        Some(start + text.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::token::Token;
+    use crate::tokenizer::tokenizer::Tokenizer;
+
+    #[test]
+    fn convert_offsets() {
+        let input = "a {\r\n  ünï: \"x\ry\";\n}\r";
+        let index = LineIndex::new(input);
+        let mut tokenizer = Tokenizer::new(input);
+        loop {
+            let (token, loc) = tokenizer.next_token_with_loc();
+            for position in &[loc.start, loc.end] {
+                assert_eq!(index.position(position.offset), *position);
+                assert_eq!(
+                    index.offset(position.line, position.column),
+                    Some(position.offset)
+                );
+            }
+            if token == Token::EOF {
+                break;
+            }
+            if let Token::Ident(name) = token {
+                assert_eq!(&input[loc.range()], name);
+            }
+        }
+        // inside `ü`
+        assert_eq!(index.position(8).column, 3);
+        assert_eq!(index.position(1000).offset, input.len());
+        assert_eq!(index.offset(1, 100), Some(3));
+        assert_eq!(index.offset(0, 1), None);
+        assert_eq!(index.offset(6, 1), None);
+    }
+
+    #[test]
+    fn count_columns() {
+        let input = "😀ü /* é */ 'ü😀' x\\ü";
+        for (encoding, columns) in &[
+            (ColumnEncoding::Utf8, [7, 8, 16, 17, 25, 26, 30]),
+            (ColumnEncoding::Utf16, [4, 5, 12, 13, 18, 19, 22]),
+            (ColumnEncoding::CodePoints, [3, 4, 11, 12, 16, 17, 20]),
+        ] {
+            let index = LineIndex::with_column_encoding(input, *encoding);
+            let mut tokenizer = Tokenizer::new(input);
+            tokenizer.column_encoding = *encoding;
+            let mut ends = vec![];
+            while tokenizer.next_token() != Token::EOF {
+                let end = tokenizer.position();
+                assert_eq!(index.position(end.offset), end);
+                assert_eq!(index.offset(end.line, end.column), Some(end.offset));
+                ends.push(end.column);
+            }
+            assert_eq!(ends, columns, "{:?}", encoding);
+        }
+        // inside the surrogate pair of `😀`
+        let index = LineIndex::with_column_encoding(input, ColumnEncoding::Utf16);
+        assert_eq!(index.offset(1, 2), Some(4));
+    }
+}
@@ -1,29 +1,204 @@
-#[derive(Debug)]
+use super::{decode_escapes, preprocess};
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Token<'a> {
-    EOF,                   // <EOF-token>
-    Ident(&'a str),        // <ident-token>
-    Function(&'a str),     // <function-token>
-    AtKeyword(&'a str),    // <at-keyword-token>
-    Hash(&'a str),         // <hash-token>
-    String(&'a str),       // <string-token>
-    BadString(&'a str),    // <bad-string-token>
-    Url(&'a str),          // <url-token>
-    BadUrl(&'a str),       // <bad-url-token>
-    Delim(&'a str),        // <delim-token>
-    Number(&'a str),       // <number-token>
-    Percentage(&'a str),   // <percentage-token>
-    Dimension(&'a str),    // <dimension-token>
-    WhiteSpace(&'a str),   // <whitespace-token>
-    CDO,                   // <CDO-token>
-    CDC,                   // <CDC-token>
-    Colon,                 // <colon-token>      :
-    Semicolon,             // <semicolon-token> ;
-    Comma,                 // <comma-token>     ,
-    LeftSquareBracket,     // <[-token>
-    RightSquareBracket,    // <]-token>
-    LeftParenthesis,       // <(-token>
-    RightParenthesis,      // <)-token>
-    LeftCurlyBracket,      // <{-token>
-    RightCurlyBracket,     // <}-token>
-    Comment(&'a str),      // <comment-token>
-}
\ No newline at end of file
+    EOF,                            // <EOF-token>
+    Ident(&'a str),                 // <ident-token>
+    Function(&'a str),              // <function-token>
+    AtKeyword(&'a str),             // <at-keyword-token>
+    Hash(&'a str, HashType),        // <hash-token>
+    String(&'a str),                // <string-token>
+    BadString(&'a str),             // <bad-string-token>
+    Url(&'a str),                   // <url-token>
+    BadUrl(&'a str),                // <bad-url-token>
+    Delim(&'a str),                 // <delim-token>
+    Number(Numeric<'a>),            // <number-token>
+    Percentage(Numeric<'a>),        // <percentage-token>
+    Dimension(Numeric<'a>),         // <dimension-token>
+    WhiteSpace(&'a str),            // <whitespace-token>
+    CDO,                            // <CDO-token>
+    CDC,                            // <CDC-token>
+    Colon,                          // <colon-token>      :
+    Semicolon,                      // <semicolon-token> ;
+    Comma,                          // <comma-token>     ,
+    LeftSquareBracket,              // <[-token>
+    RightSquareBracket,             // <]-token>
+    LeftParenthesis,                // <(-token>
+    RightParenthesis,               // <)-token>
+    LeftCurlyBracket,               // <{-token>
+    RightCurlyBracket,              // <}-token>
+    Comment(&'a str),               // <comment-token>
+    UnicodeRange(UnicodeRange<'a>), // <unicode-range-token>
+}
+
+impl<'a> Token<'a> {
+    /// the value of the token with escapes decoded, borrowed from the input when there are none,
+    /// strings lose their quotes, at-keywords and hashes their `@` and `#`,
+    /// tokens without a value give their source text
+    pub fn value(&self) -> Cow<'a, str> {
+        match *self {
+            Token::Ident(s) | Token::Function(s) => decode_escapes(s, false),
+            Token::Dimension(numeric) => decode_escapes(numeric.repr, false),
+            Token::Number(numeric) | Token::Percentage(numeric) => Cow::Borrowed(numeric.repr),
+            Token::AtKeyword(s) => decode_escapes(&s[1..], false),
+            Token::Hash(name, _) => decode_escapes(name, false),
+            Token::String(s) => decode_escapes(string_body(s), true),
+            Token::Url(s) => decode_escapes(s, false),
+            Token::BadString(s) | Token::BadUrl(s) | Token::WhiteSpace(s) | Token::Comment(s) => {
+                preprocess(s)
+            }
+            Token::Delim(s) => Cow::Borrowed(s),
+            Token::UnicodeRange(range) => Cow::Borrowed(range.value),
+            Token::EOF => Cow::Borrowed(""),
+            Token::CDO => Cow::Borrowed("<!--"),
+            Token::CDC => Cow::Borrowed("-->"),
+            Token::Colon => Cow::Borrowed(":"),
+            Token::Semicolon => Cow::Borrowed(";"),
+            Token::Comma => Cow::Borrowed(","),
+            Token::LeftSquareBracket => Cow::Borrowed("["),
+            Token::RightSquareBracket => Cow::Borrowed("]"),
+            Token::LeftParenthesis => Cow::Borrowed("("),
+            Token::RightParenthesis => Cow::Borrowed(")"),
+            Token::LeftCurlyBracket => Cow::Borrowed("{"),
+            Token::RightCurlyBracket => Cow::Borrowed("}"),
+        }
+    }
+}
+
+// a string token without its quotes, the closing one is missing at the end of the input
+fn string_body(s: &str) -> &str {
+    let quote = &s[..1];
+    let body = &s[1..];
+    match body.strip_suffix(quote) {
+        // `"a\"` ends with an escaped quote
+        Some(inner) if (inner.len() - inner.trim_end_matches('\\').len()) % 2 == 0 => inner,
+        _ => body,
+    }
+}
+
+// https://drafts.csswg.org/css-syntax-3/#consume-token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashType {
+    /// the name would start an identifier, so `#name` can be an id selector
+    Id,
+    /// like `#123` or `#-1`, still fine for a color
+    Unrestricted,
+}
+
+// https://drafts.csswg.org/css-syntax-3/#consume-numeric-token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberType {
+    Integer,
+    /// written with a fraction or an exponent, `1.0` or `1e3`
+    Number,
+}
+
+/// what a number, percentage or dimension token holds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Numeric<'a> {
+    /// the source text of the token, e.g. `+12.5px` or `50%`
+    pub repr: &'a str,
+    /// the number part of `repr`
+    pub number: &'a str,
+    pub value: f64,
+    pub number_type: NumberType,
+    /// the number starts with `+` or `-`
+    pub signed: bool,
+    /// the unit of a dimension as written, empty for numbers and percentages
+    pub unit: &'a str,
+}
+
+impl<'a> Numeric<'a> {
+    /// the unit with escapes decoded, `1\70 x` has the unit `px`
+    pub fn unit_value(&self) -> Cow<'a, str> {
+        decode_escapes(self.unit, false)
+    }
+    pub fn is_integer(&self) -> bool {
+        self.number_type == NumberType::Integer
+    }
+}
+
+// `U+0025-00FF`, `U+4??`, only produced when the tokenizer allows unicode ranges
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnicodeRange<'a> {
+    pub value: &'a str,
+    pub start: u32,
+    pub end: u32,
+}
+
+impl<'a> UnicodeRange<'a> {
+    // https://drafts.csswg.org/css-syntax-3/#urange-syntax
+    // the range must not be empty or go past the last code point
+    pub fn is_valid(&self) -> bool {
+        self.start <= self.end && self.end <= 0x10FFFF
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::tokenizer::Tokenizer;
+
+    fn values(input: &str) -> Vec<Cow<'_, str>> {
+        let mut tokenizer = Tokenizer::new(input);
+        let mut values = vec![];
+        loop {
+            match tokenizer.next_token() {
+                Token::EOF => return values,
+                Token::WhiteSpace(_) => {}
+                token => values.push(token.value()),
+            }
+        }
+    }
+
+    #[test]
+    fn decode_values() {
+        assert_eq!(
+            values(r#"quot\65  quot\e  "a\"b" 'c' @m\65 dia #i\64  url( a\)b ) 1\70 x"#),
+            vec![
+                "quote",
+                "quot\u{E}",
+                "a\"b",
+                "c",
+                "media",
+                "id",
+                "a)b",
+                "1px"
+            ]
+        );
+        assert!(matches!(values("color")[0], Cow::Borrowed("color")));
+        assert!(matches!(values("'a b'")[0], Cow::Borrowed("a b")));
+    }
+
+    #[test]
+    fn keep_escaped_whitespace_in_urls() {
+        assert_eq!(
+            values("url(a\\ ) url(a\\9 ) url( a\\9  \t)"),
+            vec!["a ", "a\t", "a\t"]
+        );
+        assert_eq!(
+            Tokenizer::new("url( a\\  )").next_token(),
+            Token::Url("a\\ ")
+        );
+    }
+
+    #[test]
+    fn decode_escaped_code_points() {
+        // one whitespace after hex digits is part of the escape, \r\n counts as one
+        assert_eq!(values("\\31 0 \\31\r\n0"), vec!["10", "10"]);
+        assert_eq!(values("\\1F600x \\é"), vec!["😀x", "é"]);
+        // zero, surrogates and code points past U+10FFFF are replaced
+        assert_eq!(
+            values("\\0  \\D800  \\110000  \\1234567"),
+            vec!["\u{FFFD}", "\u{FFFD}", "\u{FFFD}", "\u{FFFD}7"]
+        );
+        // a backslash at the end of the input
+        assert_eq!(values("a\\"), vec!["a\u{FFFD}"]);
+        assert_eq!(values("'a\\"), vec!["a"]);
+        // an escaped newline in a string is removed
+        assert_eq!(values("'a\\\nb' 'c\\\r\nd'"), vec!["ab", "cd"]);
+        assert_eq!(values(r#""a\""#), vec!["a\""]);
+        assert_eq!(values(r#""a\\""#), vec!["a\\"]);
+    }
+}
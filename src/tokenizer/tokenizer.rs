@@ -1,8 +1,14 @@
-use super::token::Token;
+use super::encoding::Decoded;
+use super::error::{TokenizerError, TokenizerErrorCallback, TokenizerErrorKind};
+use super::scan;
+use super::source_location::{ColumnEncoding, Position, SourceLocation};
+use super::token::{HashType, NumberType, Numeric, Token, UnicodeRange};
 use super::{
-    is_digit, is_hex_digit, is_identifier, is_identifier_start, is_newline, is_non_printable,
-    is_valid_escape, is_whitespace, would_start_a_number, would_start_an_identifier, utf8_is_cont_byte,
+    decode_escapes, is_digit, is_hex_digit, is_identifier, is_identifier_start, is_newline,
+    is_non_printable, is_valid_escape, is_whitespace, would_start_a_number,
+    would_start_a_unicode_range, would_start_an_identifier,
 };
+use std::collections::VecDeque;
 
 pub struct Tokenizer<'a> {
     input: &'a str,
@@ -10,16 +16,92 @@ pub struct Tokenizer<'a> {
     offset: usize,
     pub line: usize,
     pub column: usize,
+    /// read `U+0025-00FF` as one unicode-range token instead of an ident, a number and more,
+    /// the parser turns it on for the value of `unicode-range`
+    pub unicode_ranges_allowed: bool,
+    /// what `column` counts, set it before reading tokens
+    pub column_encoding: ColumnEncoding,
+    // tokens read ahead by `peek` or given back by `reconsume`
+    lookahead: VecDeque<(Token<'a>, SourceLocation)>,
+    // the last token returned
+    current: Option<(Token<'a>, SourceLocation)>,
+    /// called for every parse error of the tokenizer, the input isn't checked for errors
+    /// without it or `collect_errors`
+    pub on_error: Option<TokenizerErrorCallback>,
+    errors: Option<Vec<TokenizerError>>,
+    // errors are reported once, not again when the input is read again after `restore`
+    reported_until: usize,
+}
+
+/// a point of the token stream to go back to with `Tokenizer::restore`
+#[derive(Debug, Clone, Copy)]
+pub struct Checkpoint<'a> {
+    position: Position,
+    current: Option<(Token<'a>, SourceLocation)>,
 }
 
 impl<'a> Tokenizer<'a> {
-    pub fn new(input: &str) -> Tokenizer {
+    pub fn new(input: &'a str) -> Tokenizer<'a> {
         Tokenizer {
             input,
             position: 0,
             offset: 0,
             line: 1,
             column: 1,
+            unicode_ranges_allowed: false,
+            column_encoding: ColumnEncoding::default(),
+            lookahead: VecDeque::new(),
+            current: None,
+            on_error: None,
+            errors: None,
+            reported_until: 0,
+        }
+    }
+    /// a tokenizer over a stylesheet decoded from bytes by `encoding::decode`
+    pub fn from_decoded(decoded: &'a Decoded<'_>) -> Tokenizer<'a> {
+        Tokenizer::new(&decoded.text)
+    }
+    /// keep the parse errors of the tokenizer, they are read with `errors`
+    pub fn collect_errors(&mut self) {
+        self.errors.get_or_insert_with(Vec::new);
+    }
+    /// the parse errors found so far when `collect_errors` was called
+    pub fn errors(&self) -> &[TokenizerError] {
+        self.errors.as_deref().unwrap_or(&[])
+    }
+    #[inline]
+    fn error(&mut self, kind: TokenizerErrorKind) {
+        if (self.on_error.is_some() || self.errors.is_some()) && self.offset >= self.reported_until
+        {
+            self.report(kind);
+        }
+    }
+    #[cold]
+    fn report(&mut self, kind: TokenizerErrorKind) {
+        let error = TokenizerError {
+            kind,
+            position: self.scan_position(),
+        };
+        if let Some(callback) = self.on_error.as_mut() {
+            callback(&error);
+        }
+        if let Some(errors) = self.errors.as_mut() {
+            errors.push(error);
+        }
+    }
+    /// current position, which is the start of the next token
+    pub fn position(&self) -> Position {
+        match self.lookahead.front() {
+            Some((_, loc)) => loc.start,
+            None => self.scan_position(),
+        }
+    }
+    // where scanning goes on, past the tokens read ahead
+    fn scan_position(&self) -> Position {
+        Position {
+            offset: self.position,
+            line: self.line,
+            column: self.column,
         }
     }
     #[inline]
@@ -28,15 +110,16 @@ impl<'a> Tokenizer<'a> {
     }
     #[inline]
     fn byte(&self) -> u8 {
-        self.input.as_bytes()[self.position]
+        replace_null(self.input.as_bytes()[self.position])
     }
+    // 0 at the end of the input
     #[inline]
-    fn next(&self, idx: usize) -> u8 {
+    fn next_byte(&self, idx: usize) -> u8 {
         let pos = self.position + idx;
         if pos >= self.input.len() {
             0
         } else {
-            self.input.as_bytes()[pos]
+            replace_null(self.input.as_bytes()[pos])
         }
     }
     #[inline]
@@ -44,16 +127,45 @@ impl<'a> Tokenizer<'a> {
         let mut i = 1;
         while i <= step {
             i += 1;
+            let byte = self.byte();
             self.position += 1;
-            let byte = self.next(0);
-            if byte == b'\n' || (byte == b'\r' && self.next(1) != b'\n') {
+            if byte == b'\n' || byte == 0x0C || (byte == b'\r' && self.next_byte(0) != b'\n') {
                 self.line += 1;
                 self.column = 1;
-            } else if byte <= 0x7F || !utf8_is_cont_byte(byte) {
-                self.column += 1;
+            } else {
+                self.column += self.column_encoding.byte_columns(byte);
             }
         }
     }
+    // like `advance_update(len)` for a longer run, lines and characters are counted in bulk
+    fn advance_bulk(&mut self, len: usize) {
+        let bytes = self.input.as_bytes();
+        let end = self.position + len;
+        let mut line_start = self.position;
+        let mut i = self.position;
+        while let Some(found) = scan::find_any(&bytes[i..end], b"\n\r\x0C") {
+            let newline = i + found;
+            i = newline + 1;
+            // `\r\n` is one newline, it's counted at the `\n`
+            if bytes[newline] == b'\r' && bytes.get(i) == Some(&b'\n') {
+                continue;
+            }
+            self.line += 1;
+            self.column = 1;
+            line_start = i;
+        }
+        self.column += self.column_encoding.columns(&self.input[line_start..end]);
+        self.position = end;
+    }
+    // move past a run without newlines
+    #[inline]
+    fn advance_columns(&mut self, len: usize) {
+        let end = self.position + len;
+        self.column += self
+            .column_encoding
+            .columns(&self.input[self.position..end]);
+        self.position = end;
+    }
     #[inline]
     fn advance(&mut self, step: usize) {
         self.position += step;
@@ -67,20 +179,80 @@ impl<'a> Tokenizer<'a> {
     fn slice_str_pos(&self, pos: usize) -> &'a str {
         &self.input[pos..self.position]
     }
+    /// get next token and where it is, `loc.start.offset..loc.end.offset` slices it from the input
+    pub fn next_token_with_loc(&mut self) -> (Token<'a>, SourceLocation) {
+        let next = match self.lookahead.pop_front() {
+            Some(next) => next,
+            None => self.scan_token_with_loc(),
+        };
+        self.current = Some(next);
+        next
+    }
     /// get next token
     pub fn next_token(&mut self) -> Token<'a> {
+        self.next_token_with_loc().0
+    }
+    /// the token `n` places ahead without consuming it, `peek(0)` is the next token
+    ///
+    /// tokens read ahead don't see later changes of `unicode_ranges_allowed`
+    pub fn peek(&mut self, n: usize) -> Token<'a> {
+        self.peek_with_loc(n).0
+    }
+    pub fn peek_with_loc(&mut self, n: usize) -> (Token<'a>, SourceLocation) {
+        while self.lookahead.len() <= n {
+            let next = self.scan_token_with_loc();
+            self.lookahead.push_back(next);
+        }
+        self.lookahead[n]
+    }
+    // https://drafts.csswg.org/css-syntax-3/#reconsume-the-current-input-token
+    /// give the last token back, the next call returns it again
+    pub fn reconsume(&mut self) {
+        if let Some(current) = self.current.take() {
+            self.lookahead.push_front(current);
+        }
+    }
+    /// remember the current place to come back to it, e.g. after parsing something as a
+    /// selector that turns out to be a declaration
+    pub fn save(&self) -> Checkpoint<'a> {
+        Checkpoint {
+            position: self.position(),
+            current: self.current,
+        }
+    }
+    /// go back to a checkpoint of this tokenizer, the tokens after it are read again
+    pub fn restore(&mut self, checkpoint: Checkpoint<'a>) {
+        self.position = checkpoint.position.offset;
+        self.line = checkpoint.position.line;
+        self.column = checkpoint.position.column;
+        self.current = checkpoint.current;
+        self.lookahead.clear();
+    }
+    fn scan_token_with_loc(&mut self) -> (Token<'a>, SourceLocation) {
+        let start = self.scan_position();
+        let token = self.scan_token();
+        let end = self.scan_position();
+        self.reported_until = self.reported_until.max(end.offset);
+        (token, SourceLocation { start, end })
+    }
+    fn scan_token(&mut self) -> Token<'a> {
         if self.is_eof() {
             return Token::EOF;
         }
         self.offset = self.position;
         let code = self.byte();
-        if code == b'/' && self.next(1) == b'*' {
+        if code == b'/' && self.next_byte(1) == b'*' {
             return self.consume_comment();
         } else if is_whitespace(code) {
             return self.consume_whitespace();
         } else if is_digit(code) {
             return self.consume_numberic();
         } else if is_identifier_start(code) {
+            if self.unicode_ranges_allowed
+                && would_start_a_unicode_range(code, self.next_byte(1), self.next_byte(2))
+            {
+                return self.consume_unicode_range();
+            }
             return self.consume_ident_like();
         }
         match code {
@@ -109,15 +281,16 @@ impl<'a> Tokenizer<'a> {
     }
     fn consume_reverse_solidus(&mut self) -> Token<'a> {
         let byte = self.byte();
-        if is_valid_escape(byte, self.next(1)) {
+        if is_valid_escape(byte, self.next_byte(1)) {
             return self.consume_ident_like();
         }
+        self.error(TokenizerErrorKind::InvalidEscape);
         self.advance(1);
         Token::Delim(self.slice_str())
     }
     fn consume_at(&mut self) -> Token<'a> {
         self.advance(1);
-        if would_start_an_identifier(self.byte(), self.next(1), self.next(2)) {
+        if would_start_an_identifier(self.byte(), self.next_byte(1), self.next_byte(2)) {
             self.consume_identifier();
             return Token::AtKeyword(self.slice_str());
         }
@@ -126,7 +299,7 @@ impl<'a> Tokenizer<'a> {
     fn consume_less_than(&mut self) -> Token<'a> {
         self.advance(1);
         // <!--
-        if self.next(0) == b'!' && self.next(1) == b'-' && self.next(2) == b'-' {
+        if self.next_byte(0) == b'!' && self.next_byte(1) == b'-' && self.next_byte(2) == b'-' {
             self.advance(3);
             return Token::CDO;
         }
@@ -134,7 +307,7 @@ impl<'a> Tokenizer<'a> {
     }
     fn consume_full_stop(&mut self) -> Token<'a> {
         let byte = self.byte();
-        if would_start_a_number(byte, self.next(1), self.next(2)) {
+        if would_start_a_number(byte, self.next_byte(1), self.next_byte(2)) {
             return self.consume_numberic();
         }
         self.advance(1);
@@ -142,69 +315,77 @@ impl<'a> Tokenizer<'a> {
     }
     fn consume_hyphen_minus(&mut self) -> Token<'a> {
         let byte = self.byte();
-        let next1 = self.next(1);
-        let next2 = self.next(2);
+        let next1 = self.next_byte(1);
+        let next2 = self.next_byte(2);
         // -2
         if would_start_a_number(byte, next1, next2) {
             return self.consume_numberic();
         }
         // -->
         if next1 == b'-' && next2 == b'>' {
-            self.advance(2);
+            self.advance(3);
             return Token::CDC;
         }
         if would_start_an_identifier(byte, next1, next2) {
             return self.consume_ident_like();
         }
         self.advance(1);
-        return Token::Delim(self.slice_str());
+        Token::Delim(self.slice_str())
     }
     // https://drafts.csswg.org/css-syntax/#consume-ident-like-token
     fn consume_ident_like(&mut self) -> Token<'a> {
         let pos = self.position;
         self.consume_identifier();
         let s = &self.input[pos..self.position];
-        let url = "url";
-        if s.eq_ignore_ascii_case(url) {
-            if self.next(0) == b'(' {
-                self.advance(1);
-            }
+        if self.next_byte(0) == b'(' && decode_escapes(s, false).eq_ignore_ascii_case("url") {
+            self.advance(1);
+            // the whitespace before a quoted url is a token of its own
+            let after_parenthesis = self.scan_position();
             self.advance_to_whitespace_end();
-            let byte = self.next(0);
+            let byte = self.next_byte(0);
             if byte == b'\'' || byte == b'"' {
-                return Token::Function(url);
+                self.position = after_parenthesis.offset;
+                self.line = after_parenthesis.line;
+                self.column = after_parenthesis.column;
+                return Token::Function(s);
             }
             return self.consume_url();
         }
-        if self.next(0) == b'(' {
+        if self.next_byte(0) == b'(' {
             self.advance(1);
             return Token::Function(s);
         }
-        return Token::Ident(s);
+        Token::Ident(s)
     }
     // https://drafts.csswg.org/css-syntax/#consume-a-url-token
     fn consume_url(&mut self) -> Token<'a> {
         self.advance_to_whitespace_end();
         let pos = self.position;
+        // the url leaves out the whitespace before `)`, an escaped one stays
+        let mut end = None;
         while !self.is_eof() {
             let byte = self.byte();
             if byte == b')' {
                 break;
             } else if is_whitespace(byte) {
+                let whitespace = self.position;
                 self.advance_to_whitespace_end();
                 if self.is_eof() || self.byte() == b')' {
+                    end = Some(whitespace);
                     break;
                 } else {
                     self.consume_bad_url_remnants();
                     return Token::BadUrl(self.slice_str_pos(pos));
                 }
             } else if byte == b'"' || byte == b'\'' || byte == b'(' || is_non_printable(byte) {
+                self.error(TokenizerErrorKind::BadUrlCharacter);
                 self.consume_bad_url_remnants();
                 return Token::BadUrl(self.slice_str_pos(pos));
             } else if byte == b'\\' {
-                if is_valid_escape(byte, self.next(1)) {
+                if is_valid_escape(byte, self.next_byte(1)) {
                     self.consume_escaped();
                 } else {
+                    self.error(TokenizerErrorKind::InvalidEscape);
                     self.consume_bad_url_remnants();
                     return Token::BadUrl(self.slice_str_pos(pos));
                 }
@@ -212,16 +393,23 @@ impl<'a> Tokenizer<'a> {
                 self.advance_update(1);
             }
         }
-        Token::Url(self.slice_str_pos(pos))
+        let url = &self.input[pos..end.unwrap_or(self.position)];
+        if self.is_eof() {
+            self.error(TokenizerErrorKind::EOFInUrl);
+        } else {
+            self.advance(1);
+        }
+        Token::Url(url)
     }
     // https://drafts.csswg.org/css-syntax/#consume-remnants-of-bad-url
     fn consume_bad_url_remnants(&mut self) {
         while !self.is_eof() {
             let byte = self.byte();
             if byte == b')' {
+                self.advance(1);
                 break;
             }
-            if is_valid_escape(byte, self.next(1)) {
+            if is_valid_escape(byte, self.next_byte(1)) {
                 self.consume_escaped();
             } else {
                 self.advance_update(1);
@@ -229,7 +417,7 @@ impl<'a> Tokenizer<'a> {
         }
     }
     fn consume_plus_sign(&mut self) -> Token<'a> {
-        if would_start_a_number(self.byte(), self.next(1), self.next(2)) {
+        if would_start_a_number(self.byte(), self.next_byte(1), self.next_byte(2)) {
             return self.consume_numberic();
         }
         self.advance(1);
@@ -246,49 +434,108 @@ impl<'a> Tokenizer<'a> {
         }
     }
     fn advance_to_whitespace_end(&mut self) {
-        while !self.is_eof() {
-            let byte = self.byte();
-            if is_whitespace(byte) {
-                self.advance_update(1);
-            } else {
-                break;
-            }
-        }
+        let rest = &self.input.as_bytes()[self.position..];
+        let len = rest
+            .iter()
+            .position(|byte| !is_whitespace(*byte))
+            .unwrap_or(rest.len());
+        self.advance_bulk(len);
     }
     // https://drafts.csswg.org/css-syntax/#consume-a-number
-    fn consume_number(&mut self) {
-        let mut byte = self.byte();
+    fn consume_number(&mut self) -> NumberType {
+        let mut number_type = NumberType::Integer;
+        let byte = self.byte();
         if byte == b'+' || byte == b'-' {
             self.advance(1);
         }
         self.advance_to_decimal_end();
-        if self.next(0) == b'.' {
+        if self.next_byte(0) == b'.' && is_digit(self.next_byte(1)) {
             self.advance(1);
             self.advance_to_decimal_end();
+            number_type = NumberType::Number;
         }
-        byte = self.next(0);
+        // an `e` without digits after it starts a unit like `em`
+        let byte = self.next_byte(0);
         if byte == b'E' || byte == b'e' {
-            self.advance(1);
-            byte = self.next(0);
-            if byte == b'+' || byte == b'-' {
-                self.advance(1);
+            let sign = self.next_byte(1) == b'+' || self.next_byte(1) == b'-';
+            if is_digit(self.next_byte(1)) || (sign && is_digit(self.next_byte(2))) {
+                self.advance(if sign { 2 } else { 1 });
+                self.advance_to_decimal_end();
+                number_type = NumberType::Number;
             }
-            self.advance_to_decimal_end();
         }
+        number_type
     }
     // https://drafts.csswg.org/css-syntax/#consume-a-numeric-token
     fn consume_numberic(&mut self) -> Token<'a> {
-        self.consume_number();
-        let byte = self.next(0);
-        if would_start_an_identifier(byte, self.next(1), self.next(2)) {
+        let number_type = self.consume_number();
+        let number = self.slice_str();
+        let mut numeric = Numeric {
+            repr: number,
+            number,
+            // https://drafts.csswg.org/css-syntax-3/#convert-string-to-number
+            value: number.parse().unwrap_or(0.0),
+            number_type,
+            signed: number.starts_with(['+', '-']),
+            unit: "",
+        };
+        let byte = self.next_byte(0);
+        if would_start_an_identifier(byte, self.next_byte(1), self.next_byte(2)) {
+            let pos = self.position;
             self.consume_identifier();
-            Token::Dimension(self.slice_str())
+            numeric.unit = self.slice_str_pos(pos);
+            numeric.repr = self.slice_str();
+            Token::Dimension(numeric)
         } else if byte == b'%' {
             self.advance(1);
-            Token::Percentage(self.slice_str())
+            numeric.repr = self.slice_str();
+            Token::Percentage(numeric)
         } else {
-            Token::Number(self.slice_str())
+            Token::Number(numeric)
+        }
+    }
+    // https://drafts.csswg.org/css-syntax-3/#consume-unicode-range-token
+    fn consume_unicode_range(&mut self) -> Token<'a> {
+        // U+
+        self.advance(2);
+        let pos = self.position;
+        let digits = self.advance_to_hex_digits_end(6);
+        let mut wildcards = 0;
+        while digits + wildcards < 6 && self.next_byte(0) == b'?' {
+            self.advance(1);
+            wildcards += 1;
+        }
+        let first = self.slice_str_pos(pos);
+        if wildcards > 0 {
+            // `U+4??` is U+400-4FF
+            return Token::UnicodeRange(UnicodeRange {
+                value: self.slice_str(),
+                start: hex_value(first, 0),
+                end: hex_value(first, 0xF),
+            });
+        }
+        let start = hex_value(first, 0);
+        let mut end = start;
+        if self.next_byte(0) == b'-' && is_hex_digit(self.next_byte(1)) {
+            self.advance(1);
+            let pos = self.position;
+            self.advance_to_hex_digits_end(6);
+            end = hex_value(self.slice_str_pos(pos), 0);
         }
+        Token::UnicodeRange(UnicodeRange {
+            value: self.slice_str(),
+            start,
+            end,
+        })
+    }
+    // consume up to `max` hex digits and return how many there were
+    fn advance_to_hex_digits_end(&mut self, max: usize) -> usize {
+        let mut count = 0;
+        while count < max && is_hex_digit(self.next_byte(0)) {
+            self.advance(1);
+            count += 1;
+        }
+        count
     }
     fn consume_simple(&mut self, t: Token<'a>) -> Token<'a> {
         self.advance(1);
@@ -297,44 +544,47 @@ impl<'a> Tokenizer<'a> {
     // https://drafts.csswg.org/css-syntax/#consume-token
     fn consume_hash(&mut self) -> Token<'a> {
         self.advance(1);
-        let byte = self.next(0);
-        if is_identifier(byte) || is_valid_escape(byte, self.next(1)) {
-            // let third = self.next(2);
-            // if would_start_an_identifier(byte, next, third) {
-
-            // }
+        let byte = self.next_byte(0);
+        if is_identifier(byte) || is_valid_escape(byte, self.next_byte(1)) {
+            let hash_type = if would_start_an_identifier(byte, self.next_byte(1), self.next_byte(2))
+            {
+                HashType::Id
+            } else {
+                HashType::Unrestricted
+            };
+            let start = self.position;
             self.consume_identifier();
-            Token::Hash(self.slice_str())
+            Token::Hash(self.slice_str_pos(start), hash_type)
         } else {
             Token::Delim(self.slice_str())
         }
     }
     // https://drafts.csswg.org/css-syntax/#consume-an-identifier
     fn consume_identifier(&mut self) {
-        while !self.is_eof() {
-            let byte = self.byte();
-            if is_identifier(byte) {
-                self.advance(1);
-                continue;
-            }
-            let next = self.next(1);
-            if is_valid_escape(byte, next) {
-                self.consume_escaped();
-                continue;
+        loop {
+            let len = scan::identifier_len(&self.input.as_bytes()[self.position..]);
+            self.advance_columns(len);
+            if self.is_eof() || !is_valid_escape(self.byte(), self.next_byte(1)) {
+                break;
             }
-            break;
+            self.consume_escaped();
         }
     }
     // https://drafts.csswg.org/css-syntax/#consume-comments
     fn consume_comment(&mut self) -> Token<'a> {
         self.advance(2);
-        while !self.is_eof() {
-            if self.byte() == b'*' && self.next(1) == b'/' {
+        let bytes = self.input.as_bytes();
+        let mut i = self.position;
+        while let Some(found) = scan::find_any(&bytes[i..], b"*") {
+            i += found + 1;
+            if bytes.get(i) == Some(&b'/') {
+                self.advance_bulk(i - 1 - self.position);
                 self.advance(2);
-                break;
+                return Token::Comment(self.slice_str());
             }
-            self.advance_update(1);
         }
+        self.advance_bulk(bytes.len() - self.position);
+        self.error(TokenizerErrorKind::EOFInComment);
         Token::Comment(self.slice_str())
     }
     // https://drafts.csswg.org/css-syntax/#whitespace
@@ -346,49 +596,340 @@ impl<'a> Tokenizer<'a> {
     fn consume_string(&mut self) -> Token<'a> {
         let s = self.byte();
         self.advance(1);
-        while !self.is_eof() {
+        let stops = [s, b'\\', b'\n', b'\r', 0x0C];
+        loop {
+            let rest = &self.input.as_bytes()[self.position..];
+            let len = scan::find_any(rest, &stops).unwrap_or(rest.len());
+            self.advance_columns(len);
+            if self.is_eof() {
+                break;
+            }
             let byte = self.byte();
             if s == byte {
                 self.advance(1);
-                break;
+                return Token::String(self.slice_str());
             } else if is_newline(byte) {
+                self.error(TokenizerErrorKind::NewlineInString);
                 return Token::BadString(self.slice_str());
-            } else if byte == b'\\' {
-                let next = self.next(1);
-                if next == 0 {
-                    break;
+            } else {
+                // a backslash
+                let next = self.next_byte(1);
+                if self.position + 1 >= self.input.len() {
+                    // a backslash at the end of the input is part of the string
+                    self.advance(1);
                 } else if is_newline(next) {
-                    self.advance_update(1);
-                } else if is_valid_escape(byte, next) {
+                    // an escaped newline continues the string
+                    let len = if next == b'\r' && self.next_byte(2) == b'\n' {
+                        3
+                    } else {
+                        2
+                    };
+                    self.advance_update(len);
+                } else {
                     self.consume_escaped();
                 }
-            } else {
-                self.advance_update(1);
             }
         }
+        self.error(TokenizerErrorKind::EOFInString);
         Token::String(self.slice_str())
     }
     // https://drafts.csswg.org/css-syntax/#consume-an-escaped-code-point
-    // @TODO  check numberic is valid
-    // @TODO skip whitespace when is \r\n
+    // only moves past the escape, `Token::value` decodes it
     fn consume_escaped(&mut self) {
+        // U+005C REVERSE SOLIDUS (\)
         self.advance(1);
-        let byte = self.next(0);
+        if self.is_eof() {
+            self.error(TokenizerErrorKind::EOFInEscape);
+            return;
+        }
+        let byte = self.byte();
+        if !is_hex_digit(byte) {
+            let len = self.input[self.position..]
+                .chars()
+                .next()
+                .map_or(1, char::len_utf8);
+            self.advance_update(len);
+            return;
+        }
         self.advance(1);
-        if is_hex_digit(byte) {
-            let mut i = 0;
-            while i < 5 && !self.is_eof() {
-                let byte = self.byte();
-                if !is_hex_digit(byte) {
-                    break;
-                }
-                i += 1;
-                self.advance(1);
+        let mut i = 0;
+        while i < 5 && is_hex_digit(self.next_byte(0)) {
+            i += 1;
+            self.advance(1);
+        }
+        // a whitespace after the hex digits belongs to the escape, \r\n is one
+        if self.next_byte(0) == b'\r' && self.next_byte(1) == b'\n' {
+            self.advance_update(2);
+        } else if is_whitespace(self.next_byte(0)) {
+            self.advance_update(1);
+        }
+    }
+}
+
+// https://drafts.csswg.org/css-syntax-3/#input-preprocessing
+// U+0000 is read as U+FFFD without copying the input, 0xEF is the first byte of its UTF-8 form
+// and like U+FFFD it is a non-ASCII identifier code point, `Token::value` replaces it
+#[inline]
+fn replace_null(byte: u8) -> u8 {
+    if byte == 0 {
+        0xEF
+    } else {
+        byte
+    }
+}
+
+// the value of at most 6 hex digits, `?` stands for `wildcard`
+fn hex_value(digits: &str, wildcard: u32) -> u32 {
+    digits.bytes().fold(0, |value, byte| {
+        let digit = match byte {
+            b'?' => wildcard,
+            _ => (byte as char).to_digit(16).unwrap_or(0),
+        };
+        value * 16 + digit
+    })
+}
+
+/// the tokens up to the end of the input, without `Token::EOF`
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        match self.next_token() {
+            Token::EOF => None,
+            token => Some(token),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::preprocess;
+    use std::borrow::Cow;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn tokens(input: &str, unicode_ranges_allowed: bool) -> Vec<Token<'_>> {
+        let mut tokenizer = Tokenizer::new(input);
+        tokenizer.unicode_ranges_allowed = unicode_ranges_allowed;
+        let mut tokens = vec![];
+        loop {
+            match tokenizer.next_token() {
+                Token::EOF => return tokens,
+                Token::WhiteSpace(_) => {}
+                token => tokens.push(token),
             }
-            let byte = self.next(0);
-            if is_whitespace(byte) {
-                self.advance_update(1);
+        }
+    }
+
+    fn first(input: &str) -> Token<'_> {
+        Tokenizer::new(input).next_token()
+    }
+
+    fn range(value: &str, start: u32, end: u32) -> Token<'_> {
+        Token::UnicodeRange(UnicodeRange { value, start, end })
+    }
+
+    #[test]
+    fn peek_and_rewind() {
+        let tokens: Vec<Token> = Tokenizer::new("a:b").collect();
+        assert_eq!(tokens, [Token::Ident("a"), Token::Colon, Token::Ident("b")]);
+
+        let mut tokenizer = Tokenizer::new("a: b;");
+        assert_eq!(tokenizer.peek(2), Token::WhiteSpace(" "));
+        assert_eq!(tokenizer.peek(0), Token::Ident("a"));
+        assert_eq!(tokenizer.position().offset, 0);
+        assert_eq!(tokenizer.next_token(), Token::Ident("a"));
+        let checkpoint = tokenizer.save();
+        assert_eq!(tokenizer.next_token(), Token::Colon);
+        tokenizer.reconsume();
+        let (token, loc) = tokenizer.next_token_with_loc();
+        assert_eq!((token, loc.range()), (Token::Colon, 1..2));
+        assert_eq!(tokenizer.peek(5), Token::EOF);
+        assert_eq!(tokenizer.nth(1), Some(Token::Ident("b")));
+
+        tokenizer.restore(checkpoint);
+        assert_eq!(tokenizer.position().column, 2);
+        tokenizer.reconsume();
+        let rest: Vec<Token> = tokenizer.collect();
+        assert_eq!(
+            rest,
+            [
+                Token::Ident("a"),
+                Token::Colon,
+                Token::WhiteSpace(" "),
+                Token::Ident("b"),
+                Token::Semicolon
+            ]
+        );
+    }
+
+    #[test]
+    fn report_errors() {
+        fn errors(input: &str) -> Vec<(TokenizerErrorKind, usize)> {
+            let mut tokenizer = Tokenizer::new(input);
+            tokenizer.collect_errors();
+            tokenizer.by_ref().count();
+            let errors = tokenizer.errors().iter();
+            errors.map(|e| (e.kind, e.position.offset)).collect()
+        }
+        use TokenizerErrorKind::*;
+        assert_eq!(errors("a { b: c }"), []);
+        assert_eq!(errors("/* a"), [(EOFInComment, 4)]);
+        assert_eq!(errors("'a"), [(EOFInString, 2)]);
+        assert_eq!(errors("'a\\"), [(EOFInString, 3)]);
+        assert_eq!(errors("'a\nb'"), [(NewlineInString, 2), (EOFInString, 5)]);
+        assert_eq!(errors("a\\"), [(EOFInEscape, 2)]);
+        assert_eq!(errors("\\\n"), [(InvalidEscape, 0)]);
+        assert_eq!(errors("url(a"), [(EOFInUrl, 5)]);
+        assert_eq!(errors("url(a\"b) url(a b)"), [(BadUrlCharacter, 5)]);
+        assert_eq!(errors("url(a\\\n)"), [(InvalidEscape, 5)]);
+
+        // errors are reported once when tokens are read again
+        let mut tokenizer = Tokenizer::new("'a\n'b");
+        tokenizer.collect_errors();
+        let checkpoint = tokenizer.save();
+        tokenizer.peek(3);
+        tokenizer.restore(checkpoint);
+        tokenizer.by_ref().count();
+        assert_eq!(tokenizer.errors().len(), 2);
+
+        let messages = Rc::new(RefCell::new(vec![]));
+        let mut tokenizer = Tokenizer::new("/*");
+        let sink = Rc::clone(&messages);
+        tokenizer.on_error = Some(Box::new(move |error| {
+            sink.borrow_mut().push(error.to_string())
+        }));
+        assert_eq!(tokenizer.next_token(), Token::Comment("/*"));
+        assert_eq!(tokenizer.errors(), []);
+        assert_eq!(
+            *messages.borrow(),
+            ["Unexpected end of input in comment (1:3)"]
+        );
+    }
+
+    #[test]
+    fn consume_unicode_ranges() {
+        assert_eq!(
+            tokens("U+0025-00FF u+4?? U+10FFFF U+?????? U+1-", true),
+            vec![
+                range("U+0025-00FF", 0x25, 0xFF),
+                range("u+4??", 0x400, 0x4FF),
+                range("U+10FFFF", 0x10FFFF, 0x10FFFF),
+                range("U+??????", 0, 0xFFFFFF),
+                range("U+1", 1, 1),
+                Token::Delim("-"),
+            ]
+        );
+        // at most 6 digits and `?`, the rest is another token
+        assert_eq!(
+            tokens("U+1234567 U+4??5", true),
+            vec![
+                range("U+123456", 0x123456, 0x123456),
+                first("7"),
+                range("U+4??", 0x400, 0x4FF),
+                first("5"),
+            ]
+        );
+        assert_eq!(
+            tokens("U+0025-00FF", false),
+            vec![Token::Ident("U"), first("+0025"), first("-00FF")]
+        );
+        assert!(!UnicodeRange {
+            value: "U+??????",
+            start: 0,
+            end: 0xFFFFFF
+        }
+        .is_valid());
+    }
+
+    #[test]
+    fn count_lines_in_bulk() {
+        let input = "/* a\r\nb\rc\x0Cü */ 'ü\\\r\nd' ü-ü\\31  /*\r";
+        let mut tokenizer = Tokenizer::new(input);
+        let mut ends = vec![];
+        while tokenizer.next_token() != Token::EOF {
+            let end = tokenizer.position();
+            ends.push((end.offset, end.line, end.column));
+        }
+        assert_eq!(
+            ends,
+            [
+                (15, 4, 5),
+                (16, 4, 6),
+                (24, 5, 3),
+                (25, 5, 4),
+                (34, 5, 11),
+                (35, 5, 12),
+                (38, 6, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn consume_hashes() {
+        assert_eq!(first("#a-1"), Token::Hash("a-1", HashType::Id));
+        assert_eq!(first("#-a"), Token::Hash("-a", HashType::Id));
+        assert_eq!(first("#\\31 a"), Token::Hash("\\31 a", HashType::Id));
+        assert_eq!(first("#123"), Token::Hash("123", HashType::Unrestricted));
+        assert_eq!(first("#-1"), Token::Hash("-1", HashType::Unrestricted));
+        assert_eq!(first("#--"), Token::Hash("--", HashType::Id));
+        assert_eq!(first("# a"), Token::Delim("#"));
+        assert_eq!(first("#\\31 a").value(), "1a");
+    }
+
+    #[test]
+    fn consume_numeric_tokens() {
+        let numeric = |input| match first(input) {
+            Token::Number(n) | Token::Percentage(n) | Token::Dimension(n) => {
+                (n.number, n.value, n.number_type, n.signed, n.unit)
+            }
+            token => panic!("expected a numeric token, got {:?}", token),
+        };
+        use NumberType::*;
+        assert_eq!(numeric("12.5px"), ("12.5", 12.5, Number, false, "px"));
+        assert_eq!(numeric("+10"), ("+10", 10.0, Integer, true, ""));
+        assert_eq!(numeric("-.5e2%"), ("-.5e2", -50.0, Number, true, ""));
+        assert_eq!(numeric("1E+3"), ("1E+3", 1000.0, Number, false, ""));
+        assert_eq!(numeric("1em"), ("1", 1.0, Integer, false, "em"));
+        assert_eq!(numeric("2e-x"), ("2", 2.0, Integer, false, "e-x"));
+        match first("1\\70 x") {
+            Token::Dimension(n) => assert_eq!(n.unit_value(), "px"),
+            token => panic!("expected a dimension, got {:?}", token),
+        }
+        // a full stop without digits after it isn't part of the number
+        assert_eq!(
+            tokens("1.px", false),
+            vec![first("1"), Token::Delim("."), Token::Ident("px")]
+        );
+    }
+
+    #[test]
+    fn preprocess_input() {
+        // tokens and lines are the same for every kind of newline
+        let positions = |input: &str| {
+            let mut tokenizer = Tokenizer::new(input);
+            let mut positions = vec![];
+            loop {
+                let (token, loc) = tokenizer.next_token_with_loc();
+                if token == Token::EOF {
+                    return positions;
+                }
+                positions.push((token.value().into_owned(), loc.start.line, loc.start.column));
             }
+        };
+        let expected = positions("a\n'b\\\nc'\n/* d\n */e");
+        for newline in &["\r\n", "\r", "\x0C"] {
+            let input = "a\n'b\\\nc'\n/* d\n */e".replace('\n', newline);
+            assert_eq!(positions(&input), expected, "{:?}", newline);
+            assert_eq!(positions(&preprocess(&input)), expected, "{:?}", newline);
         }
+        // U+0000 is an identifier code point like U+FFFD
+        assert_eq!(
+            positions("a\0b -\0 \\\0"),
+            positions("a\u{FFFD}b -\u{FFFD} \\\u{FFFD}")
+        );
+        assert_eq!(preprocess("a\0\r\nb\rc\x0C"), "a\u{FFFD}\nb\nc\n");
+        assert!(matches!(preprocess("a\nb"), Cow::Borrowed(_)));
     }
 }
@@ -0,0 +1,3 @@
+pub mod transform;
+#[allow(clippy::module_inception)]
+pub mod walker;
@@ -0,0 +1,358 @@
+use super::walker::WalkAction;
+use crate::parser::node::*;
+use std::borrow::Cow;
+
+type Wrapper<'w, 'a> = Box<dyn FnOnce(Node<'a>) -> Node<'a> + 'w>;
+
+enum Replacement<'w, 'a> {
+    Node(Node<'a>),
+    Remove,
+    Wrap(Wrapper<'w, 'a>),
+}
+
+/// the changes a callback makes around the current node, they are applied when it returns
+pub struct TransformContext<'w, 'a> {
+    before: Vec<Node<'a>>,
+    after: Vec<Node<'a>>,
+    replacement: Option<Replacement<'w, 'a>>,
+}
+
+impl<'w, 'a> TransformContext<'w, 'a> {
+    fn new() -> TransformContext<'w, 'a> {
+        TransformContext {
+            before: vec![],
+            after: vec![],
+            replacement: None,
+        }
+    }
+    /// put `node` in place of the current node
+    pub fn replace(&mut self, node: Node<'a>) {
+        self.replacement = Some(Replacement::Node(node));
+    }
+    /// remove the current node, a node that isn't in a list is only removed when it's optional,
+    /// like the block of an at-rule
+    pub fn remove(&mut self) {
+        self.replacement = Some(Replacement::Remove);
+    }
+    /// insert a sibling before the current node, only for nodes in a list
+    pub fn insert_before(&mut self, node: Node<'a>) {
+        self.before.push(node);
+    }
+    /// insert a sibling after the current node, only for nodes in a list
+    pub fn insert_after(&mut self, node: Node<'a>) {
+        self.after.push(node);
+    }
+    /// put the current node into a new one, e.g. a value into a `calc()` function
+    pub fn wrap<F>(&mut self, wrapper: F)
+    where
+        F: FnOnce(Node<'a>) -> Node<'a> + 'w,
+    {
+        self.replacement = Some(Replacement::Wrap(Box::new(wrapper)));
+    }
+    fn is_replaced(&self) -> bool {
+        self.replacement.is_some()
+    }
+    // apply the changes to the node at `index` of `list`, returns the index after the new nodes
+    fn apply_to_list(self, list: &mut Vec<Node<'a>>, index: usize) -> usize {
+        if self.before.is_empty() && self.after.is_empty() && !self.is_replaced() {
+            return index + 1;
+        }
+        let current = list.remove(index);
+        let mut nodes = self.before;
+        match self.replacement {
+            None => nodes.push(current),
+            Some(Replacement::Node(node)) => nodes.push(node),
+            Some(Replacement::Remove) => {}
+            Some(Replacement::Wrap(wrapper)) => nodes.push(wrapper(current)),
+        }
+        nodes.extend(self.after);
+        let count = nodes.len();
+        list.splice(index..index, nodes);
+        index + count
+    }
+    // a node held in a field can only be replaced or wrapped, siblings are ignored
+    fn apply_to_node(self, node: &mut Node<'a>) {
+        match self.replacement {
+            Some(Replacement::Node(replacement)) => *node = replacement,
+            Some(Replacement::Wrap(wrapper)) => {
+                let placeholder = Node::Raw(Raw {
+                    value: Cow::Borrowed(""),
+                    comments: Comments::default(),
+                    loc: *node.loc(),
+                });
+                let current = std::mem::replace(node, placeholder);
+                *node = wrapper(current);
+            }
+            Some(Replacement::Remove) | None => {}
+        }
+    }
+    fn apply_to_option(self, slot: &mut Option<Box<Node<'a>>>) {
+        match (&self.replacement, slot.as_mut()) {
+            (Some(Replacement::Remove), _) => *slot = None,
+            (_, Some(node)) => self.apply_to_node(node),
+            (_, None) => {}
+        }
+    }
+}
+
+type Callback<'w, 'a> =
+    Box<dyn FnMut(&mut Node<'a>, &mut TransformContext<'w, 'a>) -> WalkAction + 'w>;
+
+/// a depth-first walk that can change the tree, the callbacks get the node and
+/// a context to replace, remove, wrap it or insert siblings next to it
+///
+/// nodes added by a callback are not walked, a node replaced in `enter` gets no `leave` call
+pub struct Transformer<'w, 'a> {
+    visit: Option<&'w str>,
+    enter: Option<Callback<'w, 'a>>,
+    leave: Option<Callback<'w, 'a>>,
+}
+
+impl<'w, 'a> Default for Transformer<'w, 'a> {
+    fn default() -> Self {
+        Transformer::new()
+    }
+}
+
+impl<'w, 'a> Transformer<'w, 'a> {
+    pub fn new() -> Transformer<'w, 'a> {
+        Transformer {
+            visit: None,
+            enter: None,
+            leave: None,
+        }
+    }
+    /// only call the callbacks for nodes of this type, the children of other nodes are still walked
+    pub fn visit(mut self, type_name: &'w str) -> Self {
+        self.visit = Some(type_name);
+        self
+    }
+    pub fn enter<F>(mut self, enter: F) -> Self
+    where
+        F: FnMut(&mut Node<'a>, &mut TransformContext<'w, 'a>) -> WalkAction + 'w,
+    {
+        self.enter = Some(Box::new(enter));
+        self
+    }
+    pub fn leave<F>(mut self, leave: F) -> Self
+    where
+        F: FnMut(&mut Node<'a>, &mut TransformContext<'w, 'a>) -> WalkAction + 'w,
+    {
+        self.leave = Some(Box::new(leave));
+        self
+    }
+    /// change `node` and its descendants, returns `false` when a callback stopped the walk
+    pub fn transform(mut self, node: &mut Node<'a>) -> bool {
+        self.transform_field(node)
+    }
+    // the changes to make around `node` and whether to go on
+    fn transform_node(&mut self, node: &mut Node<'a>) -> (TransformContext<'w, 'a>, bool) {
+        let mut context = TransformContext::new();
+        let visited = self.visit.is_none_or(|t| t == node.type_name());
+        if let (true, Some(enter)) = (visited, self.enter.as_mut()) {
+            match enter(node, &mut context) {
+                WalkAction::Continue => {}
+                WalkAction::Skip => return (context, true),
+                WalkAction::Break => return (context, false),
+            }
+            if context.is_replaced() {
+                return (context, true);
+            }
+        }
+        if !self.transform_children(node) {
+            return (context, false);
+        }
+        if let (true, Some(leave)) = (visited, self.leave.as_mut()) {
+            if leave(node, &mut context) == WalkAction::Break {
+                return (context, false);
+            }
+        }
+        (context, true)
+    }
+    fn transform_children(&mut self, node: &mut Node<'a>) -> bool {
+        match node {
+            Node::Rule(node) => {
+                self.transform_field(&mut node.prelude) && self.transform_field(&mut node.block)
+            }
+            Node::AtRule(node) => {
+                self.transform_option(&mut node.prelude) && self.transform_option(&mut node.block)
+            }
+            Node::Declaration(node) => self.transform_field(&mut node.value),
+            Node::AttributeSelector(node) => {
+                self.transform_field(&mut node.name) && self.transform_option(&mut node.value)
+            }
+            Node::Nth(node) => {
+                self.transform_field(&mut node.nth) && self.transform_option(&mut node.selector)
+            }
+            Node::MediaFeature(node) => self.transform_option(&mut node.value),
+            Node::Url(node) => self.transform_field(&mut node.value),
+            _ => match node.children_mut() {
+                Some(list) => self.transform_list(list),
+                None => true,
+            },
+        }
+    }
+    fn transform_field(&mut self, node: &mut Node<'a>) -> bool {
+        let (context, go_on) = self.transform_node(node);
+        context.apply_to_node(node);
+        go_on
+    }
+    fn transform_option(&mut self, slot: &mut Option<Box<Node<'a>>>) -> bool {
+        let (context, go_on) = match slot.as_mut() {
+            Some(node) => self.transform_node(node),
+            None => return true,
+        };
+        context.apply_to_option(slot);
+        go_on
+    }
+    // the list may grow or shrink on the way, the index always points past the changed nodes
+    fn transform_list(&mut self, list: &mut Vec<Node<'a>>) -> bool {
+        let mut index = 0;
+        while index < list.len() {
+            let (context, go_on) = self.transform_node(&mut list[index]);
+            index = context.apply_to_list(list, index);
+            if !go_on {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::generator::{generate, GeneratorOptions};
+    use crate::parser::parser::{Parser, ParserOptions};
+    use crate::tokenizer::source_location::{Position, SourceLocation};
+
+    fn transform<'w>(input: &'w str, transformer: Transformer<'w, 'w>) -> String {
+        let mut ast = Parser::new(input, ParserOptions::default()).parse().node;
+        transformer.transform(&mut ast);
+        generate(&ast, &GeneratorOptions::default())
+    }
+
+    fn identifier(name: &str) -> Node<'static> {
+        let position = Position {
+            line: 1,
+            column: 1,
+            offset: 0,
+        };
+        Node::Identifier(Identifier {
+            name: Cow::Owned(name.to_string()),
+            loc: SourceLocation {
+                start: position,
+                end: position,
+            },
+        })
+    }
+
+    #[test]
+    fn replace_and_remove() {
+        let transformer = Transformer::new()
+            .visit("Declaration")
+            .enter(|node, context| {
+                if let Node::Declaration(declaration) = node {
+                    match declaration.property.as_ref() {
+                        "color" => context.remove(),
+                        "colour" => declaration.property = Cow::Borrowed("color"),
+                        _ => {}
+                    }
+                }
+                WalkAction::Continue
+            });
+        assert_eq!(
+            transform(
+                "a { color: red; colour: blue; color: green; top: 0 }",
+                transformer
+            ),
+            "a{color:blue;top:0}"
+        );
+
+        let transformer = Transformer::new()
+            .visit("Identifier")
+            .enter(|node, context| {
+                if let Node::Identifier(ident) = node {
+                    if ident.name == "red" {
+                        context.replace(identifier("blue"));
+                    }
+                }
+                WalkAction::Continue
+            });
+        assert_eq!(
+            transform("a { color: red; b: red red }", transformer),
+            "a{color:blue;b:blue blue}"
+        );
+    }
+
+    #[test]
+    fn insert_siblings() {
+        let mut seen = vec![];
+        let transformer = Transformer::new()
+            .visit("Identifier")
+            .enter(|node, context| {
+                if let Node::Identifier(ident) = node {
+                    seen.push(ident.name.to_string());
+                    if ident.name == "b" {
+                        context.insert_before(identifier("x"));
+                        context.insert_after(identifier("y"));
+                        context.insert_after(identifier("z"));
+                    }
+                }
+                WalkAction::Continue
+            });
+        assert_eq!(transform("a { v: a b c }", transformer), "a{v:a x b y z c}");
+        // the inserted nodes aren't walked
+        assert_eq!(seen, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn wrap_nodes() {
+        let transformer = Transformer::new().visit("Value").leave(|_, context| {
+            context.wrap(|value| {
+                let loc = *value.loc();
+                let children = match value {
+                    Node::Value(value) => value.children,
+                    _ => vec![],
+                };
+                Node::Value(Value {
+                    children: vec![Node::Function(Function {
+                        name: Cow::Borrowed("calc"),
+                        children,
+                        loc,
+                    })],
+                    loc,
+                })
+            });
+            WalkAction::Continue
+        });
+        assert_eq!(
+            transform("a { width: 1px + 2px }", transformer),
+            "a{width:calc(1px + 2px)}"
+        );
+    }
+
+    #[test]
+    fn remove_optional_nodes_and_stop() {
+        let transformer = Transformer::new().visit("Block").enter(|_, context| {
+            context.remove();
+            WalkAction::Continue
+        });
+        assert_eq!(
+            transform("@media print { a { b: c } }", transformer),
+            "@media print;"
+        );
+
+        let mut count = 0;
+        let transformer = Transformer::new().visit("Rule").enter(|_, context| {
+            count += 1;
+            context.remove();
+            if count == 2 {
+                WalkAction::Break
+            } else {
+                WalkAction::Continue
+            }
+        });
+        assert_eq!(transform("a{} b{} c{}", transformer), "c{}");
+    }
+}
@@ -0,0 +1,254 @@
+use crate::parser::node::*;
+
+/// what the walk does after a callback, like `walk.skip` and `walk.break` of the JS csstree
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WalkAction {
+    Continue,
+    /// don't walk the children of the node and don't call `leave` for it, only used by `enter`
+    Skip,
+    /// stop the walk
+    Break,
+}
+
+/// the ancestors of the node passed to a callback
+pub struct WalkContext<'n, 'a> {
+    parents: Vec<&'n Node<'a>>,
+}
+
+impl<'n, 'a> WalkContext<'n, 'a> {
+    /// the ancestors from the root down to the parent
+    pub fn parents(&self) -> &[&'n Node<'a>] {
+        &self.parents
+    }
+    pub fn parent(&self) -> Option<&'n Node<'a>> {
+        self.parents.last().copied()
+    }
+    /// the closest rule around the node
+    pub fn rule(&self) -> Option<&'n Rule<'a>> {
+        self.parents.iter().rev().find_map(|node| match node {
+            Node::Rule(rule) => Some(rule),
+            _ => None,
+        })
+    }
+    /// the closest at-rule around the node
+    pub fn at_rule(&self) -> Option<&'n AtRule<'a>> {
+        self.parents.iter().rev().find_map(|node| match node {
+            Node::AtRule(at_rule) => Some(at_rule),
+            _ => None,
+        })
+    }
+    /// the declaration the node is part of
+    pub fn declaration(&self) -> Option<&'n Declaration<'a>> {
+        self.parents.iter().rev().find_map(|node| match node {
+            Node::Declaration(declaration) => Some(declaration),
+            _ => None,
+        })
+    }
+}
+
+type Callback<'w, 'n, 'a> = Box<dyn FnMut(&'n Node<'a>, &WalkContext<'n, 'a>) -> WalkAction + 'w>;
+
+// https://github.com/csstree/csstree/blob/master/docs/traversal.md#walkast-options
+/// a depth-first walk over a tree, `enter` is called before the children of a node and `leave` after them
+pub struct Walker<'w, 'n, 'a> {
+    visit: Option<&'w str>,
+    enter: Option<Callback<'w, 'n, 'a>>,
+    leave: Option<Callback<'w, 'n, 'a>>,
+}
+
+impl<'w, 'n, 'a> Default for Walker<'w, 'n, 'a> {
+    fn default() -> Self {
+        Walker::new()
+    }
+}
+
+impl<'w, 'n, 'a> Walker<'w, 'n, 'a> {
+    pub fn new() -> Walker<'w, 'n, 'a> {
+        Walker {
+            visit: None,
+            enter: None,
+            leave: None,
+        }
+    }
+    /// only call the callbacks for nodes of this type, e.g. `"Declaration"`,
+    /// the children of other nodes are still walked
+    pub fn visit(mut self, type_name: &'w str) -> Self {
+        self.visit = Some(type_name);
+        self
+    }
+    pub fn enter<F>(mut self, enter: F) -> Self
+    where
+        F: FnMut(&'n Node<'a>, &WalkContext<'n, 'a>) -> WalkAction + 'w,
+    {
+        self.enter = Some(Box::new(enter));
+        self
+    }
+    pub fn leave<F>(mut self, leave: F) -> Self
+    where
+        F: FnMut(&'n Node<'a>, &WalkContext<'n, 'a>) -> WalkAction + 'w,
+    {
+        self.leave = Some(Box::new(leave));
+        self
+    }
+    /// walk `node` and its descendants, returns `false` when a callback stopped the walk
+    pub fn walk(mut self, node: &'n Node<'a>) -> bool {
+        let mut context = WalkContext { parents: vec![] };
+        self.walk_node(node, &mut context)
+    }
+    fn walk_node(&mut self, node: &'n Node<'a>, context: &mut WalkContext<'n, 'a>) -> bool {
+        let visited = self.visit.is_none_or(|t| t == node.type_name());
+        if let (true, Some(enter)) = (visited, self.enter.as_mut()) {
+            match enter(node, context) {
+                WalkAction::Continue => {}
+                WalkAction::Skip => return true,
+                WalkAction::Break => return false,
+            }
+        }
+        context.parents.push(node);
+        for child in child_nodes(node) {
+            if !self.walk_node(child, context) {
+                return false;
+            }
+        }
+        context.parents.pop();
+        if let (true, Some(leave)) = (visited, self.leave.as_mut()) {
+            if leave(node, context) == WalkAction::Break {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// call `enter` for every node of the tree, see `Walker` for more control
+pub fn walk<'n, 'a, F>(node: &'n Node<'a>, enter: F) -> bool
+where
+    F: FnMut(&'n Node<'a>, &WalkContext<'n, 'a>) -> WalkAction,
+{
+    Walker::new().enter(enter).walk(node)
+}
+
+// all child nodes in source order, including the ones held in fields like the prelude of a rule
+fn child_nodes<'n, 'a>(node: &'n Node<'a>) -> impl Iterator<Item = &'n Node<'a>> {
+    let (first, second, list): (Option<&Node>, Option<&Node>, &[Node]) = match node {
+        Node::Rule(node) => (Some(&node.prelude), Some(&node.block), &[]),
+        Node::AtRule(node) => (node.prelude.as_deref(), node.block.as_deref(), &[]),
+        Node::Declaration(node) => (Some(&node.value), None, &[]),
+        Node::AttributeSelector(node) => (Some(&node.name), node.value.as_deref(), &[]),
+        Node::Nth(node) => (Some(&node.nth), node.selector.as_deref(), &[]),
+        Node::MediaFeature(node) => (node.value.as_deref(), None, &[]),
+        Node::Url(node) => (Some(&node.value), None, &[]),
+        _ => (None, None, node.children().map_or(&[], |c| c)),
+    };
+    first.into_iter().chain(second).chain(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parser::{Parser, ParserOptions};
+
+    fn parse(input: &str) -> Node<'_> {
+        Parser::new(input, ParserOptions::default()).parse().node
+    }
+
+    #[test]
+    fn walk_nodes() {
+        let ast =
+            parse("a { background: url(a.png), url('b.png') } @media print { b { color: red } }");
+        let mut urls = vec![];
+        walk(&ast, |node, _| {
+            if let Node::Url(url) = node {
+                urls.push(url.value.type_name());
+            }
+            WalkAction::Continue
+        });
+        assert_eq!(urls, vec!["Raw", "String"]);
+
+        let mut order = vec![];
+        let mut leaves = vec![];
+        Walker::new()
+            .visit("Rule")
+            .enter(|node, context| {
+                order.push((node.loc().start.column, context.at_rule().is_some()));
+                WalkAction::Continue
+            })
+            .leave(|node, _| {
+                leaves.push(node.loc().start.column);
+                WalkAction::Continue
+            })
+            .walk(&ast);
+        assert_eq!(order, vec![(1, false), (59, true)]);
+        assert_eq!(leaves, vec![1, 59]);
+    }
+
+    #[test]
+    fn walk_context() {
+        let ast = parse("a { color: red } b { margin: 0 } @font-face { color: blue }");
+        let mut found = vec![];
+        Walker::new()
+            .visit("Identifier")
+            .enter(|node, context| {
+                let declaration = context.declaration().map(|d| d.property.as_ref());
+                if let (Node::Identifier(ident), Some("color")) = (node, declaration) {
+                    let rule = context.rule().map(|r| r.loc.start.column);
+                    let at_rule = context.at_rule().map(|r| r.name.as_ref());
+                    let parents: Vec<&str> =
+                        context.parents().iter().map(|n| n.type_name()).collect();
+                    found.push((ident.name.to_string(), rule, at_rule, parents));
+                }
+                WalkAction::Continue
+            })
+            .walk(&ast);
+        let parents = |types: &[&'static str]| types.to_vec();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "red".to_string(),
+                    Some(1),
+                    None,
+                    parents(&["StyleSheet", "Rule", "Block", "Declaration", "Value"])
+                ),
+                (
+                    "blue".to_string(),
+                    None,
+                    Some("font-face"),
+                    parents(&["StyleSheet", "Atrule", "Block", "Declaration", "Value"])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn skip_and_break() {
+        let ast = parse("a { color: red } b { color: blue } c { color: green }");
+        let mut names = vec![];
+        let finished = walk(&ast, |node, _| match node {
+            Node::Rule(rule) if rule.loc.start.column == 1 => WalkAction::Skip,
+            Node::Identifier(ident) if ident.name == "green" => WalkAction::Break,
+            Node::Identifier(ident) => {
+                names.push(ident.name.to_string());
+                WalkAction::Continue
+            }
+            _ => WalkAction::Continue,
+        });
+        assert!(!finished);
+        assert_eq!(names, vec!["blue"]);
+
+        let mut leaves = 0;
+        let finished = Walker::new()
+            .enter(|node, _| match node {
+                Node::Block(_) => WalkAction::Skip,
+                _ => WalkAction::Continue,
+            })
+            .leave(|_, _| {
+                leaves += 1;
+                WalkAction::Continue
+            })
+            .walk(&ast);
+        assert!(finished);
+        // the stylesheet and a rule and its selector for each of the three rules
+        assert_eq!(leaves, 1 + 3 * 4);
+    }
+}
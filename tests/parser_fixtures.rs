@@ -0,0 +1,597 @@
+// runs the tokenizer and the parser against regression fixtures in the format of the
+// css-parsing-tests project, every file is a JSON array of inputs each followed by the
+// expected result, see tests/parser-fixtures/README.md
+//
+// the component value fixtures read the tokenizer, the rule and declaration ones build the
+// expected JSON from the parsed nodes, cases whose nodes don't keep everything the JSON
+// needs, like whitespace around combinators or between declarations, are known failures
+use csstree::parser::error::ParseErrorKind;
+use csstree::parser::json::{parse_json, JsonValue};
+use csstree::parser::node::*;
+use csstree::parser::parser::{ParseResult, Parser, ParserContext, ParserOptions};
+use csstree::tokenizer::token::{HashType, NumberType, Numeric, Token};
+use csstree::tokenizer::tokenizer::Tokenizer;
+use csstree::tokenizer::would_start_an_identifier;
+use std::fs;
+use std::path::Path;
+
+fn string(s: &str) -> JsonValue {
+    JsonValue::String(s.to_string())
+}
+
+fn array(items: Vec<JsonValue>) -> JsonValue {
+    JsonValue::Array(items)
+}
+
+fn error(kind: &str) -> JsonValue {
+    array(vec![string("error"), string(kind)])
+}
+
+fn numeric(name: &str, numeric: &Numeric) -> Vec<JsonValue> {
+    let number_type = match numeric.number_type {
+        NumberType::Integer => "integer",
+        NumberType::Number => "number",
+    };
+    vec![
+        string(name),
+        string(numeric.number),
+        JsonValue::Number(numeric.value),
+        string(number_type),
+    ]
+}
+
+fn token_to_json(token: Token) -> JsonValue {
+    let value = token.value();
+    match token {
+        Token::Ident(_) => array(vec![string("ident"), string(&value)]),
+        Token::AtKeyword(_) => array(vec![string("at-keyword"), string(&value)]),
+        Token::Hash(_, hash_type) => {
+            let hash_type = match hash_type {
+                HashType::Id => "id",
+                HashType::Unrestricted => "unrestricted",
+            };
+            array(vec![string("hash"), string(&value), string(hash_type)])
+        }
+        Token::String(_) => array(vec![string("string"), string(&value)]),
+        Token::Url(_) => array(vec![string("url"), string(&value)]),
+        Token::BadString(_) => error("bad-string"),
+        Token::BadUrl(_) => error("bad-url"),
+        Token::Number(n) => array(numeric("number", &n)),
+        Token::Percentage(n) => array(numeric("percentage", &n)),
+        Token::Dimension(n) => {
+            let mut items = numeric("dimension", &n);
+            items.push(string(&n.unit_value()));
+            array(items)
+        }
+        Token::UnicodeRange(range) => array(vec![
+            string("unicode-range"),
+            JsonValue::Number(range.start as f64),
+            JsonValue::Number(range.end as f64),
+        ]),
+        Token::WhiteSpace(_) => string(" "),
+        Token::RightCurlyBracket | Token::RightSquareBracket | Token::RightParenthesis => {
+            error(&value)
+        }
+        _ => string(&value),
+    }
+}
+
+// https://drafts.csswg.org/css-syntax-3/#consume-component-value
+// the values up to `end` or the end of the input, comments are dropped
+fn component_values(tokenizer: &mut Tokenizer, end: Option<Token>) -> Vec<JsonValue> {
+    let mut values = vec![];
+    loop {
+        let token = tokenizer.next_token();
+        if token == Token::EOF || Some(token) == end {
+            return values;
+        }
+        let block = |name: &str, end: Token, tokenizer: &mut Tokenizer| {
+            let mut items = vec![string(name)];
+            items.extend(component_values(tokenizer, Some(end)));
+            array(items)
+        };
+        let value = match token {
+            Token::Comment(_) => continue,
+            Token::LeftCurlyBracket => block("{}", Token::RightCurlyBracket, tokenizer),
+            Token::LeftSquareBracket => block("[]", Token::RightSquareBracket, tokenizer),
+            Token::LeftParenthesis => block("()", Token::RightParenthesis, tokenizer),
+            Token::Function(_) => {
+                let mut items = vec![string("function"), string(&token.value())];
+                items.extend(component_values(tokenizer, Some(Token::RightParenthesis)));
+                array(items)
+            }
+            _ => token_to_json(token),
+        };
+        values.push(value);
+    }
+}
+
+fn parse_component_values(input: &str) -> Vec<JsonValue> {
+    component_values(&mut Tokenizer::new(input), None)
+}
+
+// https://drafts.csswg.org/css-syntax-3/#parse-component-value
+fn parse_one_component_value(input: &str) -> JsonValue {
+    let mut values = parse_component_values(input);
+    values.retain(|value| *value != string(" "));
+    match values.len() {
+        0 => error("empty"),
+        1 => values.remove(0),
+        _ => error("extra-input"),
+    }
+}
+
+// a node the parser keeps no component values for, the case fails and is a known failure
+fn unmapped(node: &Node) -> JsonValue {
+    array(vec![string("unmapped"), string(node.type_name())])
+}
+
+// the number of a number, percentage or dimension node
+fn node_numeric<'a>(number: &'a str, unit: &'a str) -> Numeric<'a> {
+    let number_type = if number.contains(['.', 'e', 'E']) {
+        NumberType::Number
+    } else {
+        NumberType::Integer
+    };
+    Numeric {
+        repr: number,
+        number,
+        value: number.parse().unwrap_or(f64::NAN),
+        number_type,
+        signed: number.starts_with(['+', '-']),
+        unit,
+    }
+}
+
+// https://drafts.csswg.org/css-syntax-3/#consume-token
+fn hash_type(name: &str) -> HashType {
+    let bytes = name.as_bytes();
+    let byte = |i: usize| bytes.get(i).copied().unwrap_or(0);
+    if would_start_an_identifier(byte(0), byte(1), byte(2)) {
+        HashType::Id
+    } else {
+        HashType::Unrestricted
+    }
+}
+
+// the component values of value nodes, comments are dropped
+fn values_to_json(nodes: &[Node], out: &mut Vec<JsonValue>) {
+    for node in nodes {
+        let value = match node {
+            Node::Comment(_) => continue,
+            Node::Identifier(ident) => token_to_json(Token::Ident(&ident.name)),
+            Node::Function(function) => {
+                let mut items = vec![
+                    string("function"),
+                    string(&Token::Function(&function.name).value()),
+                ];
+                values_to_json(&function.children, &mut items);
+                array(items)
+            }
+            Node::Parentheses(parentheses) => {
+                let mut items = vec![string("()")];
+                values_to_json(&parentheses.children, &mut items);
+                array(items)
+            }
+            Node::Brackets(brackets) => {
+                let mut items = vec![string("[]")];
+                values_to_json(&brackets.children, &mut items);
+                array(items)
+            }
+            Node::Hash(hash) => token_to_json(Token::Hash(&hash.value, hash_type(&hash.value))),
+            Node::String(s) => token_to_json(Token::String(&s.value)),
+            Node::Url(url) => match &*url.value {
+                Node::Raw(raw) => token_to_json(Token::Url(&raw.value)),
+                Node::String(s) => array(vec![
+                    string("function"),
+                    string("url"),
+                    token_to_json(Token::String(&s.value)),
+                ]),
+                node => unmapped(node),
+            },
+            Node::Number(n) => token_to_json(Token::Number(node_numeric(&n.value, ""))),
+            Node::Percentage(n) => token_to_json(Token::Percentage(node_numeric(&n.value, ""))),
+            Node::Dimension(n) => token_to_json(Token::Dimension(node_numeric(&n.value, &n.unit))),
+            Node::Operator(operator) => string(&operator.value),
+            Node::WhiteSpace(_) => string(" "),
+            Node::CDO(_) => string("<!--"),
+            Node::CDC(_) => string("-->"),
+            _ => unmapped(node),
+        };
+        out.push(value);
+    }
+}
+
+// the component values of a type, class or id selector and so on, the whitespace around
+// combinators isn't kept
+fn selector_to_json(node: &Node, out: &mut Vec<JsonValue>) {
+    let ident = |name: &str| token_to_json(Token::Ident(name));
+    match node {
+        Node::SelectorList(list) => {
+            for (i, selector) in list.children.iter().enumerate() {
+                if i > 0 {
+                    out.push(string(","));
+                }
+                selector_to_json(selector, out);
+            }
+        }
+        Node::Selector(selector) => {
+            for child in &selector.children {
+                selector_to_json(child, out);
+            }
+        }
+        Node::TypeSelector(selector) => {
+            for (i, part) in selector.name.split('|').enumerate() {
+                if i > 0 {
+                    out.push(string("|"));
+                }
+                match part {
+                    "" => {}
+                    "*" => out.push(string("*")),
+                    part => out.push(ident(part)),
+                }
+            }
+        }
+        Node::ClassSelector(selector) => {
+            out.push(string("."));
+            out.push(ident(&selector.name));
+        }
+        Node::IdSelector(selector) => {
+            out.push(token_to_json(Token::Hash(&selector.name, HashType::Id)))
+        }
+        Node::PseudoClassSelector(PseudoClassSelector { name, children, .. })
+        | Node::PseudoElementSelector(PseudoElementSelector { name, children, .. }) => {
+            out.push(string(":"));
+            if let Node::PseudoElementSelector(_) = node {
+                out.push(string(":"));
+            }
+            match children {
+                None => out.push(ident(name)),
+                Some(children) => {
+                    let mut items =
+                        vec![string("function"), string(&Token::Function(name).value())];
+                    for child in children {
+                        selector_to_json(child, &mut items);
+                    }
+                    out.push(array(items));
+                }
+            }
+        }
+        Node::Combinator(combinator) if combinator.name == " " => out.push(string(" ")),
+        Node::Combinator(combinator) => out.push(string(&combinator.name)),
+        Node::Comment(_) => {}
+        _ => out.push(unmapped(node)),
+    }
+}
+
+fn prelude_to_json(prelude: Option<&Node>) -> JsonValue {
+    let mut items = vec![];
+    match prelude {
+        None => {}
+        Some(Node::AtRulePrelude(prelude)) => values_to_json(&prelude.children, &mut items),
+        Some(node @ Node::SelectorList(_)) => selector_to_json(node, &mut items),
+        Some(node) => items.push(unmapped(node)),
+    }
+    array(items)
+}
+
+// the component values of a block, the rules and declarations in it are written back as
+// component values, the whitespace and semicolons between them aren't kept
+fn block_to_json(block: &Node) -> JsonValue {
+    let mut items = vec![];
+    for child in block.children().into_iter().flatten() {
+        match child {
+            Node::Comment(_) => {}
+            Node::Declaration(declaration) => {
+                items.push(token_to_json(Token::Ident(&declaration.property)));
+                items.push(string(":"));
+                match &*declaration.value {
+                    Node::Value(value) => values_to_json(&value.children, &mut items),
+                    node => items.push(unmapped(node)),
+                }
+                if declaration.important {
+                    items.push(string("!"));
+                    items.push(token_to_json(Token::Ident("important")));
+                }
+            }
+            Node::Rule(rule) => {
+                if let JsonValue::Array(prelude) = prelude_to_json(Some(&rule.prelude)) {
+                    items.extend(prelude);
+                }
+                items.push(block_value(&rule.block));
+            }
+            Node::AtRule(at_rule) => {
+                items.push(token_to_json(Token::AtKeyword(&format!(
+                    "@{}",
+                    at_rule.name
+                ))));
+                if let JsonValue::Array(prelude) = prelude_to_json(at_rule.prelude.as_deref()) {
+                    items.extend(prelude);
+                }
+                match &at_rule.block {
+                    Some(block) => items.push(block_value(block)),
+                    None => items.push(string(";")),
+                }
+            }
+            node => items.push(unmapped(node)),
+        }
+    }
+    array(items)
+}
+
+// a `{}` block as one component value
+fn block_value(block: &Node) -> JsonValue {
+    let mut items = vec![string("{}")];
+    if let JsonValue::Array(values) = block_to_json(block) {
+        items.extend(values);
+    }
+    array(items)
+}
+
+fn node_to_json(node: &Node) -> JsonValue {
+    match node {
+        Node::Rule(rule) => array(vec![
+            string("qualified rule"),
+            prelude_to_json(Some(&rule.prelude)),
+            block_to_json(&rule.block),
+        ]),
+        Node::AtRule(at_rule) => array(vec![
+            string("at-rule"),
+            string(&Token::Ident(&at_rule.name).value()),
+            prelude_to_json(at_rule.prelude.as_deref()),
+            at_rule
+                .block
+                .as_deref()
+                .map_or(JsonValue::Null, block_to_json),
+        ]),
+        Node::Declaration(declaration) => {
+            let mut value = vec![];
+            match &*declaration.value {
+                Node::Value(node) => values_to_json(&node.children, &mut value),
+                node => value.push(unmapped(node)),
+            }
+            array(vec![
+                string("declaration"),
+                string(&Token::Ident(&declaration.property).value()),
+                array(value),
+                JsonValue::Bool(declaration.important),
+            ])
+        }
+        _ => error("invalid"),
+    }
+}
+
+fn list_to_json(node: &Node) -> JsonValue {
+    let children = node
+        .children()
+        .map_or(&[][..], |children| children.as_slice());
+    let items = children
+        .iter()
+        .filter(|child| !matches!(child, Node::Comment(_)))
+        .map(node_to_json)
+        .collect();
+    array(items)
+}
+
+fn parse(input: &str, context: ParserContext) -> ParseResult<'_> {
+    let options = ParserOptions {
+        context,
+        ..Default::default()
+    };
+    Parser::new(input, options).parse()
+}
+
+// an entry point for one node gives a raw node when it fails, the last error says why
+fn one_node(input: &str, context: ParserContext) -> JsonValue {
+    let result = parse(input, context);
+    if let Node::Raw(_) = result.node {
+        return match result.errors.last().map(|e| e.kind) {
+            Some(ParseErrorKind::EmptyInput) => error("empty"),
+            Some(ParseErrorKind::ExtraInput) => error("extra-input"),
+            _ => error("invalid"),
+        };
+    }
+    node_to_json(&result.node)
+}
+
+fn integer(value: &Option<std::borrow::Cow<str>>, default: f64) -> Option<f64> {
+    match value.as_deref() {
+        None => Some(default),
+        Some("+") | Some("") => Some(1.0),
+        Some("-") => Some(-1.0),
+        Some(value) => value.trim_start_matches('+').parse().ok(),
+    }
+}
+
+fn find_nth<'b, 'a>(node: &'b Node<'a>) -> Option<&'b Node<'a>> {
+    if let Node::Nth(nth) = node {
+        return Some(&nth.nth);
+    }
+    let children = match node {
+        Node::PseudoClassSelector(pseudo) => pseudo.children.as_deref()?,
+        _ => node.children()?,
+    };
+    children.iter().find_map(find_nth)
+}
+
+// https://drafts.csswg.org/css-syntax-3/#anb-microsyntax
+fn parse_an_plus_b(input: &str) -> JsonValue {
+    let selector = format!(":nth-child({})", input);
+    let result = parse(&selector, ParserContext::SelectorList);
+    if !result.errors.is_empty() {
+        return JsonValue::Null;
+    }
+    let ab = match find_nth(&result.node) {
+        Some(Node::AnPlusB(ab)) => integer(&ab.a, 0.0).zip(integer(&ab.b, 0.0)),
+        Some(Node::Identifier(ident)) if ident.name.eq_ignore_ascii_case("odd") => Some((2.0, 1.0)),
+        Some(Node::Identifier(ident)) if ident.name.eq_ignore_ascii_case("even") => {
+            Some((2.0, 0.0))
+        }
+        _ => None,
+    };
+    match ab {
+        Some((a, b)) => array(vec![JsonValue::Number(a), JsonValue::Number(b)]),
+        None => JsonValue::Null,
+    }
+}
+
+// https://drafts.csswg.org/css-syntax-3/#urange-syntax
+fn parse_unicode_range(input: &str) -> JsonValue {
+    let mut tokenizer = Tokenizer::new(input);
+    tokenizer.unicode_ranges_allowed = true;
+    let tokens: Vec<Token> = tokenizer.collect();
+    match tokens.as_slice() {
+        [Token::UnicodeRange(range)] if range.is_valid() => array(vec![
+            JsonValue::Number(range.start as f64),
+            JsonValue::Number(range.end as f64),
+        ]),
+        _ => JsonValue::Null,
+    }
+}
+
+// `known_failures` are the inputs expected to fail, they must keep failing so a fix
+// removes them from the list
+fn run(file: &str, parse: fn(&str) -> JsonValue, known_failures: &[&str]) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/parser-fixtures")
+        .join(file);
+    let text = fs::read_to_string(&path).unwrap();
+    let cases = match parse_json(&text) {
+        Ok(JsonValue::Array(cases)) => cases,
+        _ => panic!("{} isn't a JSON array", file),
+    };
+    let (mut passed, mut failed) = (0, 0);
+    for case in cases.chunks(2) {
+        let input = match &case[0] {
+            JsonValue::String(input) => input,
+            _ => panic!("{}: an input must be a string", file),
+        };
+        let actual = parse(input);
+        let known = known_failures.contains(&input.as_str());
+        if actual == case[1] && !known {
+            passed += 1;
+            println!("pass {} {:?}", file, input);
+        } else if known && actual != case[1] {
+            passed += 1;
+            println!(
+                "known failure {} {:?}\n  expected {}\n  actual   {}",
+                file,
+                input,
+                case[1].stringify(),
+                actual.stringify()
+            );
+        } else if known {
+            failed += 1;
+            println!("FIXED {} {:?} is no known failure", file, input);
+        } else {
+            failed += 1;
+            println!(
+                "FAIL {} {:?}\n  expected {}\n  actual   {}",
+                file,
+                input,
+                case[1].stringify(),
+                actual.stringify()
+            );
+        }
+    }
+    assert_eq!(
+        failed,
+        0,
+        "{} of {} cases in {} failed, {} passed or failed as known",
+        failed,
+        cases.len() / 2,
+        file,
+        passed
+    );
+}
+
+#[test]
+fn component_value_list() {
+    run(
+        "component_value_list.json",
+        |input| array(parse_component_values(input)),
+        &[],
+    );
+}
+
+#[test]
+fn one_component_value() {
+    run("one_component_value.json", parse_one_component_value, &[]);
+}
+
+#[test]
+fn declaration_list() {
+    run(
+        "declaration_list.json",
+        |input| {
+            let result = parse(input, ParserContext::DeclarationList);
+            list_to_json(&result.node)
+        },
+        // the at-rule preludes lose their whitespace, media query lists aren't mapped
+        &[
+            "@import 'foo.css'; a:b; @import 'bar.css'",
+            "@media screen { div{;}} a:b;; @media print{div{",
+        ],
+    );
+}
+
+#[test]
+fn one_declaration() {
+    run(
+        "one_declaration.json",
+        |input| one_node(input, ParserContext::Declaration),
+        &[],
+    );
+}
+
+#[test]
+fn rule_list() {
+    run(
+        "rule_list.json",
+        |input| {
+            let result = parse(input, ParserContext::RuleList);
+            list_to_json(&result.node)
+        },
+        // the preludes lose their whitespace, media query lists and raw preludes aren't mapped
+        &[
+            "<!-- --> a{}",
+            "foo {} @bar;",
+            "a{b:c}  @media x { y{} } ",
+            "@a {} b",
+            "} a{}",
+        ],
+    );
+}
+
+#[test]
+fn one_rule() {
+    run(
+        "one_rule.json",
+        |input| one_node(input, ParserContext::Rule),
+        // the preludes lose their whitespace
+        &["foo {}", " @a b; "],
+    );
+}
+
+#[test]
+fn stylesheet() {
+    run(
+        "stylesheet.json",
+        |input| {
+            let result = parse(input, ParserContext::Stylesheet);
+            list_to_json(&result.node)
+        },
+        // the at-rule prelude loses its whitespace, a raw prelude isn't mapped
+        &["@charset 'utf-8'; a{b:c}", "{}"],
+    );
+}
+
+#[test]
+fn an_plus_b() {
+    run("an+b.json", parse_an_plus_b, &[]);
+}
+
+#[test]
+fn urange() {
+    run("urange.json", parse_unicode_range, &[]);
+}